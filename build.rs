@@ -0,0 +1,4 @@
+fn main() {
+    // the starters are included in the binary, so rebuild when any of them change
+    println!("cargo:rerun-if-changed=starters");
+}
@@ -1,13 +1,24 @@
-use std::{fs, path::PathBuf};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
-use minijinja::Environment;
+use minijinja::{context, Environment, Value};
 use pulldown_cmark::Options;
 
 use crate::{
-    config::{Config, ConfigDefaults, ConfigStructure},
-    error::Result,
-    templates,
-    tree::{load_contents, Dir},
+    assets::Manifest,
+    config::{Config, ConfigAssets, ConfigDefaults, ConfigSearch, ConfigStructure},
+    data::load_data,
+    error::{self, Error, Result},
+    functions,
+    images::{Images, IMAGE_DIR},
+    languages::Language,
+    report::Report,
+    search, templates,
+    tree::{link_translations, load_contents, Dir},
 };
 
 /// The app represents the state of the site to generate
@@ -16,57 +27,191 @@ pub struct App<'a> {
     options: Options,
     defaults: &'a ConfigDefaults,
     templates: Environment<'a>,
-    content: Dir<PathBuf, PathBuf>,
-    assets: Dir<PathBuf, PathBuf>,
+    /// the content of each language, starting with the default language
+    content: Vec<(Language, Dir<PathBuf, PathBuf>)>,
+    /// the asset directories paired with their contents, later directories override earlier ones
+    assets: Vec<(PathBuf, Dir<PathBuf, PathBuf>)>,
+    asset_options: &'a ConfigAssets,
+    minify: bool,
+    /// the assets as copied to the site, for looking them up from the templates
+    manifest: Arc<Manifest>,
+    images: Arc<Images>,
+    search: &'a ConfigSearch,
+    /// the path of the base url, for the urls in the search index
+    url_prefix: String,
+    drafts: bool,
+    /// how long it took to load the site
+    load_time: Duration,
 }
 
 impl<'a> App<'a> {
-    pub fn new(config: &'a Config) -> Self {
+    pub fn new(config: &'a Config) -> Result<Self> {
+        let start = Instant::now();
         let structure = &config.structure;
         let options = config.options.options();
         let defaults = &config.defaults;
 
-        let templates = templates::get_env(&structure.template).unwrap();
-        let content = load_contents(&structure.content).unwrap();
-        let assets = load_contents(&structure.assets).unwrap();
+        let mut templates = templates::get_env(&config.template_dirs(), &config.templates)?;
+        let manifest = Arc::new(Manifest::default());
+        let images = Arc::new(Images::new(config));
+        functions::register(&mut templates, config, manifest.clone(), images.clone());
+        let data = load_data(&structure.data)?;
+        templates.add_global(
+            "site",
+            context! { data, ..Value::from_serialize(&config.site) },
+        );
+        let main_content = load_contents(&structure.content)
+            .map_err(|e| Error::from(e).in_file(&structure.content))?;
+        let content = Language::all(config)
+            .into_iter()
+            .map(|language| {
+                let content = match &language.content {
+                    Some(dir) => load_contents(dir)
+                        .map_err(|e| Error::from(e).in_file(dir))?
+                        .in_language(&language),
+                    None => main_content.in_language(&language),
+                };
+                Ok((language, content))
+            })
+            .collect::<Result<_>>()?;
+        let assets = config
+            .theme_assets()
+            .into_iter()
+            .chain([PathBuf::from(&structure.assets)])
+            .filter(|dir| dir.is_dir())
+            .map(|dir| {
+                load_contents(&dir)
+                    .map(|contents| (dir.clone(), contents))
+                    .map_err(|e| Error::from(e).in_file(&dir))
+            })
+            .collect::<Result<_>>()?;
 
-        App {
+        Ok(App {
             structure,
             options,
             defaults,
             templates,
             content,
             assets,
-        }
+            asset_options: &config.assets,
+            minify: config.minify,
+            manifest,
+            images,
+            search: &config.search,
+            url_prefix: functions::url_prefix(&config.site.base_url),
+            drafts: config.drafts,
+            load_time: start.elapsed(),
+        })
     }
 
     fn clear_dirs(&self) -> Result<()> {
-        let _ = fs::remove_dir_all(&self.structure.site)
-            .and_then(|_| fs::remove_dir_all(&self.structure.work));
+        let _ = fs::remove_dir_all(&self.structure.site);
+        // the resized images are kept so they are only made once
+        if let Ok(entries) = fs::read_dir(&self.structure.work) {
+            for entry in entries.flatten() {
+                let path = entry.path();
+                if entry.file_name() == IMAGE_DIR {
+                    continue;
+                }
+                let _ = if path.is_dir() {
+                    fs::remove_dir_all(path)
+                } else {
+                    fs::remove_file(path)
+                };
+            }
+        }
         Ok(())
     }
 
-    fn copy_assets(&self) -> Result<()> {
-        let res = self.assets.copy_to(&self.structure.site)?;
-        Ok(res)
+    /// copy the assets to the site, giving how many were copied
+    fn copy_assets(&self) -> Result<usize> {
+        let out_dir = Path::new(&self.structure.site).join(&self.structure.assets);
+        let files: Vec<_> = self
+            .assets
+            .iter()
+            .flat_map(|(dir, assets)| {
+                assets
+                    .files()
+                    .into_iter()
+                    .map(move |file| (asset_name(dir, file), file.clone()))
+            })
+            .collect();
+        self.manifest
+            .copy(&files, out_dir, self.asset_options, self.minify)
     }
 
-    fn create_pages(&self) -> Result<()> {
-        let parsed_tree = self.content.annotate(&self.defaults, &self.options)?;
-        let meta_tree = parsed_tree.write_metadata(&self.structure.work)?;
-        meta_tree.create(&self.structure.site, &self.templates)?;
+    fn create_pages(&self, report: &mut Report) -> Result<()> {
+        let start = Instant::now();
+        let parsed_trees = self.content.iter().map(|(language, content)| {
+            content.annotate(
+                self.defaults,
+                &self.options,
+                &self.templates,
+                &self.images,
+                language,
+            )
+        });
+        let mut parsed_trees = error::collect(parsed_trees)?;
+        if !self.drafts {
+            report.drafts = parsed_trees.iter_mut().map(Dir::remove_drafts).sum();
+        }
+        link_translations(&mut parsed_trees);
+        report.phase("annotate", start.elapsed());
+        if self.structure.write_work {
+            let start = Instant::now();
+            for tree in &parsed_trees {
+                tree.write_metadata(&self.structure.work)?;
+            }
+            report.phase("write_metadata", start.elapsed());
+        }
+        let start = Instant::now();
+        let sites = error::collect(
+            parsed_trees
+                .into_iter()
+                .map(|tree| tree.render_contents(&self.templates, &self.options, &self.images)),
+        )?;
+        let times = error::collect(
+            sites
+                .iter()
+                .map(|site| site.create(&self.structure.site, &self.templates, self.minify)),
+        )?;
+        report.rendered(times.into_iter().flatten().collect());
+        report.phase("render", start.elapsed());
+        if self.search.enabled {
+            let start = Instant::now();
+            search::write_index(&sites, self.search, &self.url_prefix, &self.structure.site)?;
+            report.phase("search", start.elapsed());
+        }
         Ok(())
     }
 
     fn create_dirs(&self) -> Result<()> {
-        fs::create_dir_all(&self.structure.work)?;
+        if self.structure.write_work {
+            fs::create_dir_all(&self.structure.work)?;
+        }
         fs::create_dir_all(&self.structure.site)?;
         Ok(())
     }
 
-    pub fn create(&self) -> Result<()> {
-        self.clear_dirs()
-            .and_then(|_| self.create_dirs())
-            .and_then(|_| self.copy_assets().and_then(|_| self.create_pages()))
+    /// generate the site, giving a report of what was done and how long it took
+    pub fn create(&self) -> Result<Report> {
+        let mut report = Report::default();
+        report.phase("load", self.load_time);
+        self.clear_dirs()?;
+        self.create_dirs()?;
+        let start = Instant::now();
+        report.assets = self.copy_assets()?;
+        report.phase("copy", start.elapsed());
+        self.create_pages(&mut report)?;
+        Ok(report)
     }
 }
+
+/// the name of an asset from its path in the asset directory, like `css/default.css`
+fn asset_name(dir: &Path, file: &Path) -> String {
+    let path = file.strip_prefix(dir).unwrap_or(file);
+    path.components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
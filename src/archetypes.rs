@@ -0,0 +1,128 @@
+/// archetypes are the templates for new pages made with `sitdown add`
+///
+/// the archetype for `logs/my-new-post.md` is the closest of `archetypes/logs.md` and then
+/// `archetypes/default.md`, looked for in the site and then its theme, with a built in default
+/// if there aren't any
+/// bundles like `logs/my-trip/index.md` are named after their directory
+/// archetypes are rendered with
+///     title   the title from the name of the page, `My New Post`
+///     date    the current date and time
+///     slug    the name of the page, `my-new-post`
+///     section the directory of the page in the content, `logs`
+///     site    the values about the site from the config
+use std::{
+    ffi::OsStr,
+    fs,
+    path::{Component, Path, PathBuf},
+};
+
+use chrono::{Local, SecondsFormat};
+use minijinja::{context, Environment};
+
+use crate::{
+    config::{Config, ARCHETYPE_DIR},
+    error::{Error, Result},
+    tree::BUNDLE_FILE,
+};
+
+/// the archetype used for pages without one of their own
+const DEFAULT_NAME: &str = "default";
+
+/// the archetype used when the site doesn't have any
+pub const DEFAULT_ARCHETYPE: &str = r#"---
+title: "{{ title }}"
+date: {{ date }}
+draft: true
+---
+"#;
+
+/// create a new page in the content at `path` from its archetype, giving where it was saved
+pub fn add(config: &Config, path: &str) -> Result<PathBuf> {
+    let path = PathBuf::from(path.trim_start_matches('/'));
+    // keep the page inside the content
+    if !path
+        .components()
+        .all(|c| matches!(c, Component::Normal(_) | Component::CurDir))
+    {
+        return Err(Error::NewPageError(format!(
+            "`{}` is outside of the content",
+            path.display()
+        )));
+    }
+    let mut path: PathBuf = path
+        .components()
+        .filter(|c| matches!(c, Component::Normal(_)))
+        .collect();
+    if path.extension().is_none() {
+        path.set_extension("md");
+    }
+    let save = Path::new(&config.structure.content).join(&path);
+    if save.exists() {
+        return Err(Error::NewPageError(format!(
+            "`{}` already exists",
+            save.display()
+        )));
+    }
+    // the page of a bundle is named after its directory, and is in the section above it
+    let page = match path.parent() {
+        Some(parent) if path.file_name() == Some(OsStr::new(BUNDLE_FILE)) => parent,
+        _ => &path,
+    };
+    let slug = page
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .ok_or_else(|| Error::PageError(path.clone()))?
+        .to_string();
+    let section = page.parent().unwrap_or(Path::new(""));
+    let (name, archetype) = match find(config, section) {
+        Some(archetype) => (
+            archetype.display().to_string(),
+            fs::read_to_string(&archetype)?,
+        ),
+        None => (DEFAULT_NAME.to_string(), DEFAULT_ARCHETYPE.to_string()),
+    };
+    let mut env = Environment::new();
+    env.set_debug(true);
+    env.set_keep_trailing_newline(true);
+    env.add_template_owned(name.clone(), archetype)?;
+    let contents = env.get_template(&name)?.render(context! {
+        title => title_of(&slug),
+        date => Local::now().to_rfc3339_opts(SecondsFormat::Secs, true),
+        section => section.to_string_lossy(),
+        slug,
+        site => &config.site,
+    })?;
+    if let Some(parent) = save.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(&save, contents)?;
+    Ok(save)
+}
+
+/// the archetype for a page in `section`, the archetype of the closest section is used
+fn find(config: &Config, section: &Path) -> Option<PathBuf> {
+    let mut dirs = vec![PathBuf::from(&config.structure.archetypes)];
+    dirs.extend(config.theme_dir().map(|theme| theme.join(ARCHETYPE_DIR)));
+    section
+        .ancestors()
+        .filter(|s| !s.as_os_str().is_empty())
+        .map(|s| s.with_extension("md"))
+        .chain([PathBuf::from(DEFAULT_NAME).with_extension("md")])
+        .flat_map(|name| dirs.iter().map(move |dir| dir.join(&name)))
+        .find(|archetype| archetype.is_file())
+}
+
+/// the title for a page from its name, `my-new-post` to `My New Post`
+fn title_of(slug: &str) -> String {
+    slug.split(['-', '_', ' '])
+        .filter(|word| !word.is_empty())
+        .map(|word| {
+            let mut chars = word.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect(),
+                None => String::new(),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
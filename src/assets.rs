@@ -0,0 +1,216 @@
+/// copying the assets to the site, along with a manifest of their hashes
+///
+/// with fingerprinting on each asset is also copied to a name with a hash of its contents, so
+/// `css/default.css` is also written to `css/default.3f9a1c.css`, and the `asset` function in the
+/// templates gives the hashed name so caches are refreshed when the file changes
+/// the plain name is kept so assets can still refer to each other by path
+///
+/// sass files, `.scss` and `.sass`, are compiled to css with the same name, and partials starting
+/// with `_` are only used through imports
+/// the source maps for them only point to the sources, as the compiler doesn't track where the
+/// css came from
+///
+/// when minifying the css and js assets are minified before they are hashed
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::{PoisonError, RwLock},
+};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use globset::{GlobBuilder, GlobSetBuilder};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+use crate::{
+    config::ConfigAssets,
+    error::{Error, Result},
+    minify,
+};
+
+/// the file in the output assets directory mapping the assets to their hashed names
+pub const MANIFEST_FILE: &str = "manifest.json";
+/// the number of hex characters of the hash put in the file names
+const HASH_LEN: usize = 6;
+
+/// an asset copied to the site
+#[derive(Debug, Clone, Serialize)]
+pub struct Asset {
+    /// the location of the asset in the output assets directory
+    pub path: String,
+    /// the subresource integrity hash, for the `integrity` attribute of links and scripts
+    pub integrity: String,
+}
+
+/// the assets copied to the site by their path in the assets directory, filled in when the assets
+/// are copied so the templates can look them up while rendering
+#[derive(Debug, Default)]
+pub struct Manifest {
+    assets: RwLock<BTreeMap<String, Asset>>,
+}
+
+impl Manifest {
+    /// the asset at `path` in the assets directory, like `css/default.css`
+    pub fn get(&self, path: &str) -> Option<Asset> {
+        let assets = self.assets.read().unwrap_or_else(PoisonError::into_inner);
+        assets.get(path.trim_start_matches('/')).cloned()
+    }
+
+    /// copy the files to the output assets directory, files later in the list replace earlier
+    /// ones with the same path
+    pub fn copy<T: AsRef<Path>>(
+        &self,
+        files: &[(String, PathBuf)],
+        out_dir: T,
+        config: &ConfigAssets,
+        minify: bool,
+    ) -> Result<usize> {
+        let out_dir = out_dir.as_ref();
+        let mut skip = GlobSetBuilder::new();
+        for glob in &config.minify_skip {
+            skip.add(GlobBuilder::new(glob).literal_separator(true).build()?);
+        }
+        let skip = skip.build()?;
+        let files: BTreeMap<_, _> = files
+            .iter()
+            .filter(|(_, source)| !is_partial(source))
+            .map(|(name, source)| (output_name(name), source.clone()))
+            .collect();
+        let mut assets = BTreeMap::new();
+        let mut errors = Vec::new();
+        for (name, source) in files {
+            let minify = minify && !skip.is_match(&name);
+            match copy_file(&name, &source, out_dir, config, minify) {
+                Ok(asset) => {
+                    assets.insert(name, asset);
+                }
+                Err(err) => errors.push(err.in_file(&source)),
+            }
+        }
+        if config.fingerprint {
+            fs::create_dir_all(out_dir)?;
+            fs::write(
+                out_dir.join(MANIFEST_FILE),
+                serde_json::to_string_pretty(&assets)?,
+            )?;
+        }
+        let copied = assets.len();
+        *self.assets.write().unwrap_or_else(PoisonError::into_inner) = assets;
+        if errors.is_empty() {
+            Ok(copied)
+        } else {
+            Err(Error::from_errors(errors))
+        }
+    }
+}
+
+/// copy a single asset, and to its hashed name if fingerprinting
+fn copy_file(
+    name: &str,
+    source: &Path,
+    out_dir: &Path,
+    config: &ConfigAssets,
+    minify: bool,
+) -> Result<Asset> {
+    let save = out_dir.join(name);
+    if let Some(parent) = save.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let contents = if is_sass(source) {
+        compile_sass(source, &save, config)?.into_bytes()
+    } else {
+        fs::read(source)?
+    };
+    let mut contents = if minify {
+        minify::asset(name, contents)
+    } else {
+        contents
+    };
+    // added after minifying so the comment isn't taken out
+    if is_sass(source) && config.embed_sass_sources {
+        let map = map_path(&save);
+        let map_name = map.file_name().unwrap_or_default().to_string_lossy();
+        contents.extend(format!("\n/*# sourceMappingURL={map_name} */\n").into_bytes());
+    }
+    let digest = Sha256::digest(&contents);
+    fs::write(&save, &contents)?;
+    let path = if config.fingerprint {
+        let hash: String = digest.iter().map(|b| format!("{b:02x}")).collect();
+        let hashed = hashed_name(name, &hash[..HASH_LEN]);
+        fs::write(out_dir.join(&hashed), &contents)?;
+        hashed
+    } else {
+        name.to_string()
+    };
+    Ok(Asset {
+        path,
+        integrity: format!("sha256-{}", STANDARD.encode(digest)),
+    })
+}
+
+/// compile the sass file to css, writing a map with the sass source next to the css at `save` if
+/// asked for, grass can't give the mappings between the lines so the map only has the source
+fn compile_sass(source: &Path, save: &Path, config: &ConfigAssets) -> Result<String> {
+    let options = grass::Options::default().load_paths(&config.sass_include);
+    let css = grass::from_path(source, &options)?;
+    if config.embed_sass_sources {
+        // the css is saved next to where the sass is in the assets, so the sass has the same
+        // name relative to the map
+        let source_map = serde_json::json!({
+            "version": 3,
+            "file": save.file_name().unwrap_or_default().to_string_lossy(),
+            "sources": [source.file_name().unwrap_or_default().to_string_lossy()],
+            "sourcesContent": [fs::read_to_string(source)?],
+            "names": [],
+            "mappings": "",
+        });
+        fs::write(map_path(save), source_map.to_string())?;
+    }
+    Ok(css)
+}
+
+/// the source map for the css at `save`, `main.css` to `main.css.map`
+fn map_path(save: &Path) -> PathBuf {
+    let mut map = save.as_os_str().to_owned();
+    map.push(".map");
+    PathBuf::from(map)
+}
+
+/// whether the file is sass to compile to css
+fn is_sass(path: &Path) -> bool {
+    matches!(
+        path.extension().and_then(|e| e.to_str()),
+        Some("scss" | "sass")
+    )
+}
+
+/// whether the file is a sass partial, only used by importing it into other sass files
+fn is_partial(path: &Path) -> bool {
+    is_sass(path)
+        && path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .is_some_and(|n| n.starts_with('_'))
+}
+
+/// the name of the asset in the output, sass files become css files
+fn output_name(name: &str) -> String {
+    match name.rsplit_once('.') {
+        Some((stem, "scss" | "sass")) => format!("{stem}.css"),
+        _ => name.to_string(),
+    }
+}
+
+/// put the hash before the extension of the file name, `css/default.css` to
+/// `css/default.3f9a1c.css`
+fn hashed_name(name: &str, hash: &str) -> String {
+    let (dir, file) = match name.rsplit_once('/') {
+        Some((dir, file)) => (format!("{dir}/"), file),
+        None => (String::new(), name),
+    };
+    match file.rsplit_once('.') {
+        Some((stem, ext)) if !stem.is_empty() => format!("{dir}{stem}.{hash}.{ext}"),
+        _ => format!("{dir}{file}.{hash}"),
+    }
+}
@@ -1,4 +1,10 @@
-use figment::{Error, Figment, Metadata, Provider};
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use figment::{value::Value, Error, Figment, Metadata, Provider};
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
 use pulldown_cmark::Options;
 use serde::{Deserialize, Serialize};
 
@@ -8,13 +14,40 @@ pub const OUT_DIR: &str = "_site";
 pub const ASSET_DIR: &str = "assets";
 pub const TEMPLATE_DIR: &str = "templates";
 pub const WORK_DIR: &str = "_work";
+pub const DATA_DIR: &str = "data";
+pub const THEME_DIR: &str = "themes";
+pub const ARCHETYPE_DIR: &str = "archetypes";
+/// the defaults provided by a theme, found in the root of the theme
+pub const THEME_FILE: &str = "theme.toml";
+/// the language of the content when the site doesn't say
+pub const DEFAULT_LANGUAGE: &str = "en";
 
 /// config for managing the site
 #[derive(Debug, Default, Serialize, Deserialize)]
 pub struct Config {
+    /// the name of the theme in the themes directory to build on
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub theme: Option<String>,
+    /// minify the pages and the css and js assets written to the site
+    #[serde(default)]
+    pub minify: bool,
+    /// render the pages marked with `draft: true`, which are left out otherwise
+    #[serde(default)]
+    pub drafts: bool,
+    /// the language of the pages without a language in their name, `en` if not given
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_language: Option<String>,
+    /// the other languages of the site by their code, like `de`
+    #[serde(default)]
+    pub languages: BTreeMap<String, ConfigLanguage>,
     pub structure: ConfigStructure,
     pub options: ConfigOptions,
     pub defaults: ConfigDefaults,
+    pub site: ConfigSite,
+    pub templates: ConfigTemplates,
+    pub assets: ConfigAssets,
+    pub images: ConfigImages,
+    pub search: ConfigSearch,
 }
 
 /// config for defining the layout of the site
@@ -26,10 +59,19 @@ pub struct ConfigStructure {
     pub assets: String,
     /// the jinja templates
     pub template: String,
-    /// the dir that metadata gets written to and read from
+    /// the dir that metadata gets written to when `write_work` is set
     pub work: String,
+    /// write the metadata of the pages out to the work dir, for debugging the site
+    #[serde(default)]
+    pub write_work: bool,
     /// the output directory that is used for serving the pages
     pub site: String,
+    /// structured data files made available to the templates
+    pub data: String,
+    /// the directory holding the themes
+    pub themes: String,
+    /// the templates for new pages made with `sitdown add`
+    pub archetypes: String,
 }
 
 /// config defining the defaults to be used in the site generation
@@ -37,6 +79,130 @@ pub struct ConfigStructure {
 pub struct ConfigDefaults {
     /// the default template for a page
     pub page: String,
+    /// the default template for the index page of a directory
+    pub section: String,
+    /// defaults for the pages with paths matching a glob, later rules take precedence
+    #[serde(default)]
+    pub rules: ConfigRules,
+}
+
+/// the rules of the defaults, with their globs compiled when the config is loaded
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(try_from = "Vec<ConfigRule>", into = "Vec<ConfigRule>")]
+pub struct ConfigRules {
+    rules: Vec<ConfigRule>,
+    globs: GlobSet,
+}
+
+/// defaults for the pages matching a path glob
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConfigRule {
+    /// the glob for the path of the page within the content directory, like `logs/**`
+    pub glob: String,
+    /// the template for the matching pages
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub template: Option<String>,
+    /// the metadata for the matching pages
+    #[serde(default)]
+    pub meta: BTreeMap<String, Value>,
+}
+
+/// config for the values about the site that are given to every template as `site`
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ConfigSite {
+    /// the title of the site
+    #[serde(default)]
+    pub title: String,
+    /// a short description of the site
+    #[serde(default)]
+    pub description: String,
+    /// the url the site is served from
+    #[serde(default)]
+    pub base_url: String,
+    /// the author of the site
+    #[serde(default)]
+    pub author: String,
+    /// any other values to give to the templates
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, Value>,
+}
+
+/// config for a language of the site
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ConfigLanguage {
+    /// the name of the language, like `Deutsch`
+    #[serde(default)]
+    pub name: String,
+    /// a content directory with only the pages in this language, instead of naming the pages in
+    /// the main content like `page.de.md`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content: Option<String>,
+    /// the strings for the `trans` template function by their key
+    #[serde(default)]
+    pub strings: BTreeMap<String, String>,
+}
+
+/// config for how the templates are loaded
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ConfigTemplates {
+    /// load the templates from disk as they are used instead of all up front
+    pub lazy: bool,
+    /// the extensions to try, in order, when a template is named without one
+    pub extensions: Vec<String>,
+}
+
+/// config for how the assets are copied to the site
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ConfigAssets {
+    /// also copy the assets to names with a hash of their contents, and link to those from the
+    /// templates so caches are refreshed when the assets change
+    pub fingerprint: bool,
+    /// directories to look in for sass imports, besides the directory of the importing file
+    pub sass_include: Vec<String>,
+    /// write a `.map` next to the compiled sass with the sass source in it for the browser tools,
+    /// it doesn't map the css lines back to the sass lines
+    pub embed_sass_sources: bool,
+    /// globs for the assets to leave as they are when minifying, like `js/vendor/**`
+    pub minify_skip: Vec<String>,
+}
+
+/// config for resizing the images of the site
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConfigImages {
+    /// the widths to resize the images in the markdown to for their `srcset`
+    pub widths: Vec<u32>,
+    /// the format of the resized images, `jpeg`, `webp`, `png`, or `avif`, webp images are
+    /// lossless so `quality` doesn't apply to them
+    pub format: String,
+    /// the quality of lossy formats, from 1 to 100
+    pub quality: u8,
+}
+
+/// config for the search index of the site
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ConfigSearch {
+    /// write the search index to the site
+    pub enabled: bool,
+    /// the name of the index in the output directory
+    pub file: String,
+    /// the sections to index, like `posts` or `docs/guides`, all of them when empty
+    pub sections: Vec<String>,
+    /// the fields of each page to put in the index
+    pub fields: Vec<SearchField>,
+    /// the most characters of the body of a page to index, no limit when 0
+    pub max_body: usize,
+}
+
+/// a field of a page in the search index
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchField {
+    Title,
+    Url,
+    Headings,
+    Body,
+    Tags,
+    Lang,
 }
 
 /// config options for the markdown parsing
@@ -53,7 +219,11 @@ impl Default for ConfigStructure {
             assets: ASSET_DIR.into(),
             template: TEMPLATE_DIR.into(),
             work: WORK_DIR.into(),
+            write_work: false,
             site: OUT_DIR.into(),
+            data: DATA_DIR.into(),
+            themes: THEME_DIR.into(),
+            archetypes: ARCHETYPE_DIR.into(),
         }
     }
 }
@@ -62,6 +232,54 @@ impl Default for ConfigDefaults {
     fn default() -> Self {
         Self {
             page: "content".into(),
+            section: "section".into(),
+            rules: ConfigRules::default(),
+        }
+    }
+}
+
+impl Default for ConfigRules {
+    fn default() -> Self {
+        Self {
+            rules: Vec::new(),
+            globs: GlobSet::empty(),
+        }
+    }
+}
+
+impl Default for ConfigTemplates {
+    fn default() -> Self {
+        Self {
+            lazy: false,
+            extensions: vec!["html".into(), "jinja".into(), "j2".into(), "xml".into()],
+        }
+    }
+}
+
+impl Default for ConfigImages {
+    fn default() -> Self {
+        Self {
+            widths: vec![480, 960, 1440],
+            format: "jpeg".into(),
+            quality: 80,
+        }
+    }
+}
+
+impl Default for ConfigSearch {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            file: "search_index.json".into(),
+            sections: Vec::new(),
+            fields: vec![
+                SearchField::Title,
+                SearchField::Url,
+                SearchField::Headings,
+                SearchField::Body,
+                SearchField::Tags,
+            ],
+            max_body: 2000,
         }
     }
 }
@@ -76,11 +294,38 @@ impl Config {
     pub fn figment() -> Figment {
         Figment::from(Self::default())
     }
+    #[allow(clippy::result_large_err)]
     pub fn from<T: Provider>(provider: T) -> Result<Self, Error> {
         Figment::from(provider).extract()
     }
 }
 
+impl Config {
+    /// the directory of the theme the site uses
+    pub fn theme_dir(&self) -> Option<PathBuf> {
+        self.theme
+            .as_ref()
+            .map(|theme| Path::new(&self.structure.themes).join(theme))
+    }
+
+    /// the template directories for the site, the site's own templates come before the theme's
+    pub fn template_dirs(&self) -> Vec<PathBuf> {
+        let mut dirs = vec![PathBuf::from(&self.structure.template)];
+        dirs.extend(self.theme_dir().map(|theme| theme.join(TEMPLATE_DIR)));
+        dirs
+    }
+
+    /// the asset directory of the theme the site uses
+    pub fn theme_assets(&self) -> Option<PathBuf> {
+        self.theme_dir().map(|theme| theme.join(ASSET_DIR))
+    }
+
+    /// the code of the language of the pages without a language in their name
+    pub fn default_language(&self) -> &str {
+        self.default_language.as_deref().unwrap_or(DEFAULT_LANGUAGE)
+    }
+}
+
 impl Provider for Config {
     fn metadata(&self) -> Metadata {
         Metadata::named("Sitdown config")
@@ -119,6 +364,48 @@ impl Provider for ConfigStructure {
 //     }
 // }
 
+impl ConfigDefaults {
+    /// the defaults from the rules that match the path of a page
+    pub fn matching<T: AsRef<Path>>(&self, path: T) -> BTreeMap<String, Value> {
+        let mut defaults = BTreeMap::new();
+        // the matches are in the order of the rules, so later rules take precedence
+        for index in self.rules.globs.matches(path.as_ref()) {
+            let rule = &self.rules.rules[index];
+            if let Some(template) = &rule.template {
+                defaults.insert("template".into(), template.as_str().into());
+            }
+            defaults.extend(rule.meta.clone());
+        }
+        defaults
+    }
+}
+
+impl TryFrom<Vec<ConfigRule>> for ConfigRules {
+    type Error = globset::Error;
+
+    /// compile the globs of the rules, a `*` doesn't match across directories
+    fn try_from(rules: Vec<ConfigRule>) -> Result<Self, Self::Error> {
+        let mut globs = GlobSetBuilder::new();
+        for rule in &rules {
+            globs.add(
+                GlobBuilder::new(&rule.glob)
+                    .literal_separator(true)
+                    .build()?,
+            );
+        }
+        Ok(Self {
+            globs: globs.build()?,
+            rules,
+        })
+    }
+}
+
+impl From<ConfigRules> for Vec<ConfigRule> {
+    fn from(value: ConfigRules) -> Self {
+        value.rules
+    }
+}
+
 impl Provider for ConfigDefaults {
     fn metadata(&self) -> figment::Metadata {
         Metadata::named("Sitdown defaults")
@@ -128,6 +415,51 @@ impl Provider for ConfigDefaults {
     }
 }
 
+impl Provider for ConfigSite {
+    fn metadata(&self) -> Metadata {
+        Metadata::named("Sitdown site values")
+    }
+    fn data(&self) -> Result<figment::value::Map<figment::Profile, figment::value::Dict>, Error> {
+        figment::providers::Serialized::defaults(Self::default()).data()
+    }
+}
+
+impl Provider for ConfigTemplates {
+    fn metadata(&self) -> Metadata {
+        Metadata::named("Sitdown template loading")
+    }
+    fn data(&self) -> Result<figment::value::Map<figment::Profile, figment::value::Dict>, Error> {
+        figment::providers::Serialized::defaults(Self::default()).data()
+    }
+}
+
+impl Provider for ConfigAssets {
+    fn metadata(&self) -> Metadata {
+        Metadata::named("Sitdown asset handling")
+    }
+    fn data(&self) -> Result<figment::value::Map<figment::Profile, figment::value::Dict>, Error> {
+        figment::providers::Serialized::defaults(Self::default()).data()
+    }
+}
+
+impl Provider for ConfigImages {
+    fn metadata(&self) -> Metadata {
+        Metadata::named("Sitdown image resizing")
+    }
+    fn data(&self) -> Result<figment::value::Map<figment::Profile, figment::value::Dict>, Error> {
+        figment::providers::Serialized::defaults(Self::default()).data()
+    }
+}
+
+impl Provider for ConfigSearch {
+    fn metadata(&self) -> Metadata {
+        Metadata::named("Sitdown search index")
+    }
+    fn data(&self) -> Result<figment::value::Map<figment::Profile, figment::value::Dict>, Error> {
+        figment::providers::Serialized::defaults(Self::default()).data()
+    }
+}
+
 impl ConfigOptions {
     // fn figment() -> Figment {
     //     Figment::from(Self::default())
@@ -154,3 +486,73 @@ impl Provider for ConfigOptions {
         figment::providers::Serialized::defaults(Self::default()).data()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use figment::providers::{Format, Toml};
+
+    use super::*;
+
+    fn config(toml: &str) -> Config {
+        Config::from(Config::figment().merge(Toml::string(toml))).unwrap()
+    }
+
+    #[test]
+    fn rules_compiled_when_loaded() {
+        let config = config(
+            r#"
+            [[defaults.rules]]
+            glob = "logs/**"
+            template = "log"
+            "#,
+        );
+        let defaults = config.defaults.matching("logs/2024/first.md");
+        assert_eq!(defaults["template"], Value::from("log"));
+    }
+
+    #[test]
+    fn star_stays_in_its_directory() {
+        let config = config(
+            r#"
+            [[defaults.rules]]
+            glob = "api/*.md"
+            meta = { kind = "api" }
+            "#,
+        );
+        assert!(config.defaults.matching("api/list.md").contains_key("kind"));
+        assert!(config.defaults.matching("api/v1/list.md").is_empty());
+    }
+
+    #[test]
+    fn later_rules_win() {
+        let config = config(
+            r#"
+            [[defaults.rules]]
+            glob = "**"
+            template = "page"
+            meta = { kind = "any", toc = true }
+
+            [[defaults.rules]]
+            glob = "docs/**"
+            meta = { kind = "doc" }
+            "#,
+        );
+        let defaults = config.defaults.matching("docs/intro.md");
+        assert_eq!(defaults["template"], Value::from("page"));
+        assert_eq!(defaults["kind"], Value::from("doc"));
+        assert_eq!(defaults["toc"], Value::from(true));
+        assert_eq!(
+            config.defaults.matching("about.md")["kind"],
+            Value::from("any")
+        );
+    }
+
+    #[test]
+    fn bad_glob_fails_to_load() {
+        let toml = r#"
+            [[defaults.rules]]
+            glob = "logs/[a"
+            "#;
+        assert!(Config::from(Config::figment().merge(Toml::string(toml))).is_err());
+    }
+}
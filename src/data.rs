@@ -0,0 +1,75 @@
+/// structured data files that are made available to every template as `site.data`
+///
+/// each file becomes an entry named by its file stem, so `data/team.yaml` is `site.data.team`,
+/// and subdirectories become nested entries, so `data/releases/v1.toml` is
+/// `site.data.releases.v1`
+use std::{collections::BTreeMap, fs, path::Path};
+
+use minijinja::Value;
+
+use crate::error::{Error, Result};
+
+/// read all the data files in the directory, a missing directory has no data
+pub fn load_data<T: AsRef<Path>>(path: T) -> Result<BTreeMap<String, Value>> {
+    let path = path.as_ref();
+    let mut data = BTreeMap::new();
+    if !path.is_dir() {
+        return Ok(data);
+    }
+    let mut errors = Vec::new();
+    for entry in path.read_dir()? {
+        let entry = entry?;
+        let path = entry.path();
+        let name = match path.file_stem().and_then(|s| s.to_str()) {
+            Some(name) if !name.starts_with('.') => name.to_string(),
+            _ => continue,
+        };
+        let value = if path.is_dir() {
+            load_data(&path).map(|d| Some(Value::from(d)))
+        } else {
+            load_file(&path).map_err(|e| e.in_file(&path))
+        };
+        match value {
+            Ok(Some(value)) => {
+                data.insert(name, value);
+            }
+            Ok(None) => {}
+            Err(err) => errors.push(err),
+        }
+    }
+    if errors.is_empty() {
+        Ok(data)
+    } else {
+        Err(Error::from_errors(errors))
+    }
+}
+
+/// parse a single data file based on its extension, unknown formats are skipped
+pub fn load_file<T: AsRef<Path>>(path: T) -> Result<Option<Value>> {
+    let path = path.as_ref();
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or_default();
+    let value = match extension {
+        "toml" => Value::from_serialize(toml::from_str::<toml::Value>(&fs::read_to_string(path)?)?),
+        "yaml" | "yml" => Value::from_serialize(serde_yml::from_str::<serde_yml::Value>(
+            &fs::read_to_string(path)?,
+        )?),
+        "json" => Value::from_serialize(serde_json::from_str::<serde_json::Value>(
+            &fs::read_to_string(path)?,
+        )?),
+        "csv" => {
+            let mut reader = csv::Reader::from_path(path)?;
+            // sorted by the column names so the rows come out the same on every build
+            let rows: std::result::Result<Vec<BTreeMap<String, String>>, _> =
+                reader.deserialize().collect();
+            Value::from_serialize(rows?)
+        }
+        _ => {
+            log::warn!("Skipping `{path:?}`, not a known data format");
+            return Ok(None);
+        }
+    };
+    Ok(Some(value))
+}
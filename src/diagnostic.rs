@@ -0,0 +1,149 @@
+/// diagnostics for reporting errors with where in the site they came from
+///
+/// renders like
+///     error: Failed to parse yaml: `did not find expected key`
+///      --> content/logs/daily.md:3:1
+///       |
+///     3 | date 2001-01-01
+///       | ^
+///       = help: check that the frontmatter between the `---` lines is valid yaml
+use std::{
+    fmt::{self, Display},
+    io::{self, IsTerminal, Write},
+};
+
+use crate::error::{Category, Error};
+
+const RED: &str = "\x1b[1;31m";
+const BLUE: &str = "\x1b[1;34m";
+const CYAN: &str = "\x1b[1;36m";
+const BOLD: &str = "\x1b[1m";
+const RESET: &str = "\x1b[0m";
+
+/// an error along with the location it happened at and what might fix it
+#[derive(Debug, Default)]
+pub struct Diagnostic {
+    /// what went wrong
+    pub message: String,
+    /// the file, or template, the error is in
+    pub path: Option<String>,
+    /// the contents of the file, for showing the line with the error
+    pub source: Option<String>,
+    /// the byte offset of the error in the source, used if the line isn't known
+    pub offset: Option<usize>,
+    /// the line of the error, starting from 1
+    pub line: Option<usize>,
+    /// the column of the error, starting from 1
+    pub column: Option<usize>,
+    /// a suggestion for fixing the error
+    pub help: Option<String>,
+    /// any other context for the error
+    pub notes: Vec<String>,
+    /// what kind of thing failed, kept from the error the diagnostic was made from
+    pub category: Category,
+}
+
+impl Diagnostic {
+    pub fn new<T: Into<String>>(message: T) -> Self {
+        Self {
+            message: message.into(),
+            ..Default::default()
+        }
+    }
+
+    pub fn with_help<T: Into<String>>(mut self, help: T) -> Self {
+        self.help = Some(help.into());
+        self
+    }
+
+    /// the line and column of the error, from the offset if they aren't known directly
+    fn location(&self) -> Option<(usize, usize)> {
+        if let Some(line) = self.line {
+            return Some((line, self.column.unwrap_or(1)));
+        }
+        let source = self.source.as_ref()?;
+        let offset = self.offset?.min(source.len());
+        let before = source.get(..offset)?;
+        let line = before.matches('\n').count() + 1;
+        let column = before
+            .rsplit('\n')
+            .next()
+            .unwrap_or_default()
+            .chars()
+            .count()
+            + 1;
+        Some((line, column))
+    }
+
+    /// render the diagnostic, with colors for a terminal
+    pub fn render(&self, color: bool) -> String {
+        let paint = |style: &'static str| if color { style } else { "" };
+        let (red, blue, cyan, bold, reset) = (
+            paint(RED),
+            paint(BLUE),
+            paint(CYAN),
+            paint(BOLD),
+            paint(RESET),
+        );
+
+        let mut out = format!("{red}error{reset}{bold}: {}{reset}\n", self.message);
+        let location = self.location();
+        let snippet = location.and_then(|(line, _)| {
+            self.source
+                .as_ref()
+                .and_then(|source| source.lines().nth(line.checked_sub(1)?))
+        });
+        let width = location
+            .map(|(line, _)| line.to_string().len())
+            .unwrap_or(1);
+        let pad = " ".repeat(width);
+        if let Some(path) = &self.path {
+            match location {
+                Some((line, column)) => {
+                    out += &format!("{pad}{blue}-->{reset} {path}:{line}:{column}\n")
+                }
+                None => out += &format!("{pad}{blue}-->{reset} {path}\n"),
+            }
+        }
+        if let (Some((line, column)), Some(snippet)) = (location, snippet) {
+            let marker = " ".repeat(column.saturating_sub(1));
+            out += &format!("{pad} {blue}|{reset}\n");
+            out += &format!("{blue}{line:>width$} |{reset} {snippet}\n");
+            out += &format!("{pad} {blue}|{reset} {marker}{red}^{reset}\n");
+        }
+        if let Some(help) = &self.help {
+            out += &format!("{pad} {blue}={reset} {cyan}help{reset}: {help}\n");
+        }
+        for note in &self.notes {
+            out += &format!("{pad} {blue}={reset} {bold}note{reset}: {note}\n");
+        }
+        out
+    }
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.render(false))
+    }
+}
+
+/// print out all the errors to stderr
+pub fn report(err: Error) {
+    let color = io::stderr().is_terminal();
+    let errors = err.into_diagnostics();
+    let mut stderr = io::stderr().lock();
+    for diagnostic in &errors {
+        let _ = writeln!(stderr, "{}", diagnostic.render(color));
+    }
+    let (red, bold, reset) = if color {
+        (RED, BOLD, RESET)
+    } else {
+        ("", "", "")
+    };
+    let plural = if errors.len() == 1 { "" } else { "s" };
+    let _ = writeln!(
+        stderr,
+        "{red}error{reset}{bold}: failed due to {} error{plural}{reset}",
+        errors.len()
+    );
+}
@@ -1,9 +1,43 @@
-use std::path::PathBuf;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
 
+use minijinja::ErrorKind;
 use thiserror::Error;
 
+use crate::diagnostic::Diagnostic;
+
 pub type Result<A> = std::result::Result<A, Error>;
 
+/// what kind of thing failed, so the cli can exit with a different code for each
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Category {
+    #[default]
+    Other,
+    /// the config of the site
+    Config,
+    /// the content, data, or assets of the site
+    Content,
+    /// the templates
+    Template,
+    /// reading or writing files
+    Io,
+}
+
+impl Category {
+    /// the exit code for the cli, clap already uses 2 for bad arguments
+    pub fn exit_code(self) -> i32 {
+        match self {
+            Category::Other => 1,
+            Category::Config => 3,
+            Category::Content => 4,
+            Category::Template => 5,
+            Category::Io => 6,
+        }
+    }
+}
+
 #[derive(Debug, Error)]
 pub enum Error {
     #[error("Encountered io error: `{0}`")]
@@ -14,10 +48,243 @@ pub enum Error {
     DirError(PathBuf),
     #[error("Failed to parse yaml: `{0}`")]
     SerdeError(serde_yml::Error),
+    #[error("Failed to parse toml: `{0}`")]
+    TomlError(toml::de::Error),
+    #[error("Failed to parse json: `{0}`")]
+    JsonError(serde_json::Error),
+    #[error("Failed to parse csv: `{0}`")]
+    CsvError(csv::Error),
+    #[error("Invalid glob: `{0}`")]
+    GlobError(globset::Error),
     #[error("Error with templating: `{0}`")]
     JinjaError(minijinja::Error),
+    #[error("Failed to compile sass: `{0}`")]
+    SassError(Box<grass::Error>),
+    #[error("Failed to process image: {0}")]
+    ImageError(String),
+    #[error("Malformed shortcode: {0}")]
+    ShortcodeError(String),
+    #[error("Failed to create the site: {0}")]
+    NewSiteError(String),
+    #[error("Failed to create the page: {0}")]
+    NewPageError(String),
     #[error("Error watching files: `{0}`")]
     NotifyError(notify::Error),
+    #[error("Failed to read config: `{0}`")]
+    ConfigError(Box<figment::Error>),
+    #[error("{}", .0.message)]
+    Diagnostic(Box<Diagnostic>),
+    #[error("Encountered {} errors", .0.len())]
+    Multiple(Vec<Error>),
+}
+
+impl Error {
+    /// what kind of thing failed, for several errors the kind of the first one
+    pub fn category(&self) -> Category {
+        match self {
+            Error::Diagnostic(diagnostic) => diagnostic.category,
+            Error::Multiple(errors) => errors.first().map(Error::category).unwrap_or_default(),
+            Error::ConfigError(_) | Error::GlobError(_) => Category::Config,
+            Error::PageError(_)
+            | Error::DirError(_)
+            | Error::SerdeError(_)
+            | Error::TomlError(_)
+            | Error::JsonError(_)
+            | Error::CsvError(_)
+            | Error::SassError(_)
+            | Error::ImageError(_)
+            | Error::ShortcodeError(_) => Category::Content,
+            Error::JinjaError(_) => Category::Template,
+            Error::IOError(_) | Error::NotifyError(_) => Category::Io,
+            Error::NewSiteError(_) | Error::NewPageError(_) => Category::Other,
+        }
+    }
+
+    /// the exit code for the cli from what failed
+    pub fn exit_code(&self) -> i32 {
+        self.category().exit_code()
+    }
+
+    /// combine several errors into one
+    pub fn from_errors<I: IntoIterator<Item = Error>>(errors: I) -> Self {
+        let mut all = Vec::new();
+        for err in errors {
+            match err {
+                Error::Multiple(errs) => all.extend(errs),
+                err => all.push(err),
+            }
+        }
+        if all.len() == 1 {
+            all.remove(0)
+        } else {
+            Error::Multiple(all)
+        }
+    }
+
+    /// mark the error as coming from the file, so it can show where in the file it happened
+    pub fn in_file<T: AsRef<Path>>(self, path: T) -> Self {
+        let path = path.as_ref();
+        match self {
+            Error::Multiple(errors) => {
+                Error::Multiple(errors.into_iter().map(|e| e.in_file(path)).collect())
+            }
+            err => {
+                let mut diagnostic = err.into_diagnostic();
+                match &diagnostic.path {
+                    Some(existing) if Path::new(existing) == path => {}
+                    Some(_) => diagnostic.notes.push(format!("in `{}`", path.display())),
+                    None => {
+                        diagnostic.path = Some(path.display().to_string());
+                        diagnostic.source = fs::read_to_string(path).ok();
+                    }
+                }
+                Error::Diagnostic(Box::new(diagnostic))
+            }
+        }
+    }
+
+    /// mark the error as happening at the byte offset in the file it comes from
+    pub fn at_offset(self, offset: usize) -> Self {
+        let mut diagnostic = self.into_diagnostic();
+        diagnostic.offset = Some(offset);
+        Error::Diagnostic(Box::new(diagnostic))
+    }
+
+    /// add some context to the error
+    pub fn note<T: Into<String>>(self, note: T) -> Self {
+        let note = note.into();
+        match self {
+            Error::Multiple(errors) => {
+                Error::Multiple(errors.into_iter().map(|e| e.note(note.clone())).collect())
+            }
+            err => {
+                let mut diagnostic = err.into_diagnostic();
+                diagnostic.notes.push(note);
+                Error::Diagnostic(Box::new(diagnostic))
+            }
+        }
+    }
+
+    /// all the errors as diagnostics
+    pub fn into_diagnostics(self) -> Vec<Diagnostic> {
+        match self {
+            Error::Multiple(errors) => errors
+                .into_iter()
+                .flat_map(|e| e.into_diagnostics())
+                .collect(),
+            err => vec![err.into_diagnostic()],
+        }
+    }
+
+    /// convert the error into a diagnostic, pulling out the location and giving a suggestion
+    /// where possible
+    pub fn into_diagnostic(self) -> Diagnostic {
+        let category = self.category();
+        let message = self.to_string();
+        let diagnostic = match self {
+            Error::Diagnostic(diagnostic) => *diagnostic,
+            Error::SerdeError(err) => Diagnostic {
+                offset: err.location().map(|l| l.index()),
+                ..Diagnostic::new(message)
+                    .with_help("check that the frontmatter between the `---` lines is valid yaml")
+            },
+            Error::TomlError(err) => Diagnostic {
+                message: format!("Failed to parse toml: `{}`", err.message()),
+                offset: err.span().map(|s| s.start),
+                ..Default::default()
+            },
+            Error::JsonError(err) => Diagnostic {
+                line: Some(err.line()),
+                column: Some(err.column()),
+                ..Diagnostic::new(message)
+            },
+            Error::CsvError(err) => Diagnostic {
+                line: err.position().map(|p| p.line() as usize),
+                ..Diagnostic::new(message)
+            },
+            Error::JinjaError(err) => {
+                let help = match err.kind() {
+                    ErrorKind::UndefinedError => {
+                        "check the spelling of the value or give it a default with `|default(...)`"
+                    }
+                    ErrorKind::TemplateNotFound => {
+                        "templates are named by their path in the template directory, with or without the extension"
+                    }
+                    ErrorKind::SyntaxError => "check that all the tags are closed and balanced",
+                    _ => "check the template near the marked location",
+                };
+                let message = match err.detail() {
+                    Some(detail) => format!("Error with templating: {}: {detail}", err.kind()),
+                    None => format!("Error with templating: {}", err.kind()),
+                };
+                Diagnostic {
+                    path: err.name().map(String::from),
+                    source: err.template_source().map(String::from),
+                    offset: err.range().map(|r| r.start),
+                    line: err.range().is_none().then(|| err.line()).flatten(),
+                    ..Diagnostic::new(message).with_help(help)
+                }
+            }
+            Error::SassError(err) => match err.kind() {
+                grass::ErrorKind::ParseError { message, loc, .. } => Diagnostic {
+                    path: Some(loc.file.name().to_string()),
+                    source: Some(loc.file.source().to_string()),
+                    line: Some(loc.begin.line + 1),
+                    column: Some(loc.begin.column + 1),
+                    ..Diagnostic::new(format!("Failed to compile sass: {message}"))
+                        .with_help("imports are looked for next to the file and in `sass_include`")
+                },
+                _ => Diagnostic::new(message),
+            },
+            Error::ShortcodeError(_) => Diagnostic::new(message).with_help(
+                "shortcodes look like `{{< name key=\"value\" >}}` and need a template in `templates/shortcodes`",
+            ),
+            Error::GlobError(_) => Diagnostic::new(message)
+                .with_help("globs look like `logs/**` or `api/*.md` relative to the content"),
+            Error::ConfigError(err) => Diagnostic {
+                path: err
+                    .metadata
+                    .as_ref()
+                    .and_then(|m| m.source.as_ref())
+                    .map(|s| s.to_string()),
+                ..Diagnostic::new(message)
+            },
+            _ => Diagnostic::new(message),
+        };
+        Diagnostic {
+            category,
+            ..diagnostic
+        }
+    }
+}
+
+/// collect the results, keeping all of the errors rather than stopping at the first
+pub fn collect<T, I: IntoIterator<Item = Result<T>>>(results: I) -> Result<Vec<T>> {
+    let mut values = Vec::new();
+    let mut errors = Vec::new();
+    for result in results {
+        match result {
+            Ok(value) => values.push(value),
+            Err(err) => errors.push(err),
+        }
+    }
+    if errors.is_empty() {
+        Ok(values)
+    } else {
+        Err(Error::from_errors(errors))
+    }
+}
+
+impl From<Box<grass::Error>> for Error {
+    fn from(value: Box<grass::Error>) -> Self {
+        Self::SassError(value)
+    }
+}
+
+impl From<image::ImageError> for Error {
+    fn from(value: image::ImageError) -> Self {
+        Self::ImageError(value.to_string())
+    }
 }
 
 impl From<std::io::Error> for Error {
@@ -31,11 +298,36 @@ impl From<serde_yml::Error> for Error {
         Self::SerdeError(value)
     }
 }
+impl From<toml::de::Error> for Error {
+    fn from(value: toml::de::Error) -> Self {
+        Self::TomlError(value)
+    }
+}
+impl From<serde_json::Error> for Error {
+    fn from(value: serde_json::Error) -> Self {
+        Self::JsonError(value)
+    }
+}
+impl From<csv::Error> for Error {
+    fn from(value: csv::Error) -> Self {
+        Self::CsvError(value)
+    }
+}
+impl From<globset::Error> for Error {
+    fn from(value: globset::Error) -> Self {
+        Self::GlobError(value)
+    }
+}
 impl From<minijinja::Error> for Error {
     fn from(value: minijinja::Error) -> Self {
         Self::JinjaError(value)
     }
 }
+impl From<figment::Error> for Error {
+    fn from(value: figment::Error) -> Self {
+        Self::ConfigError(Box::new(value))
+    }
+}
 impl From<notify::Error> for Error {
     fn from(value: notify::Error) -> Self {
         Self::NotifyError(value)
@@ -0,0 +1,242 @@
+/// the site specific filters and functions made available to the templates
+///
+/// functions:
+///     url_for(path, lang) the url for a page or directory in the content, like `logs/daily.md`,
+///                         in the default language unless another language is given
+///     trans(key, lang)    the string for the key from the config of the language, defaulting to
+///                         the language of the page, falling back to the default language
+///     asset(path)         the url for a file in the assets, like `css/default.css`
+///     integrity(path)     the subresource integrity hash for a file in the assets
+///     resize_image(path, width, format)
+///                         resize an image in the assets or content, giving its url, width, and
+///                         height, the format defaults to the one in the image config
+///     abs_url(path)       the url prefixed with the `base_url` of the site
+///     get_page(path)      the data for a page in the content
+///     get_section(path)   the data for a directory in the content
+///     load_data(path)     the data from a toml, yaml, json, or csv file in the site
+/// filters:
+///     markdown            render markdown to html
+///     date(format)        format a date, like `2001-01-01`, with a strftime style format
+///     slugify             make text suitable for a url
+///     truncate_words(n)   keep the first `n` words of the text
+/// tests:
+///     date                whether the value is a date the `date` filter can format
+use std::{collections::BTreeMap, fmt::Write, path::Path, sync::Arc};
+
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime};
+use minijinja::{Environment, Error, ErrorKind, State, Value};
+use pulldown_cmark::Parser;
+
+use crate::{
+    assets::Manifest, config::Config, data, images::Images, languages::Language, tree::Section,
+};
+
+/// add the filters and functions to the template environment
+pub fn register(
+    env: &mut Environment,
+    config: &Config,
+    manifest: Arc<Manifest>,
+    images: Arc<Images>,
+) {
+    let prefix = url_prefix(&config.site.base_url);
+    let base_url = config.site.base_url.trim_end_matches('/').to_string();
+    let assets = config.structure.assets.trim_matches('/').to_string();
+    let options = config.options.options();
+
+    let languages: BTreeMap<_, _> = Language::all(config)
+        .into_iter()
+        .map(|language| (language.code.clone(), language))
+        .collect();
+    let default_language = config.default_language().to_string();
+
+    let url_prefix = prefix.clone();
+    let url_languages = languages.clone();
+    env.add_function("url_for", move |path: &str, lang: Option<&str>| {
+        let Some(lang) = lang else {
+            return Ok(url_for(&url_prefix, path));
+        };
+        let language = url_languages
+            .get(lang)
+            .ok_or_else(|| unknown_language(lang))?;
+        let path = language.prefix.join(path.trim_matches('/'));
+        Ok(url_for(&url_prefix, &path.to_string_lossy()))
+    });
+    env.add_function(
+        "trans",
+        move |state: &State, key: &str, lang: Option<&str>| {
+            let lang = match lang {
+                Some(lang) => lang.to_string(),
+                None => state
+                    .lookup("lang")
+                    .and_then(|lang| lang.as_str().map(String::from))
+                    .unwrap_or_else(|| default_language.clone()),
+            };
+            let language = languages
+                .get(&lang)
+                .ok_or_else(|| unknown_language(&lang))?;
+            language
+                .strings
+                .get(key)
+                .or_else(|| languages.get(&default_language)?.strings.get(key))
+                .cloned()
+                .ok_or_else(|| {
+                    Error::new(
+                        ErrorKind::InvalidOperation,
+                        format!("no string `{key}` for the language `{lang}`"),
+                    )
+                })
+        },
+    );
+    let assets_manifest = manifest.clone();
+    env.add_function("asset", move |path: &str| {
+        let path = assets_manifest
+            .get(path)
+            .map(|asset| asset.path)
+            .unwrap_or_else(|| path.trim_start_matches('/').to_string());
+        format!("{prefix}/{assets}/{path}")
+    });
+    env.add_function("integrity", move |path: &str| {
+        manifest
+            .get(path)
+            .map(|asset| asset.integrity)
+            .ok_or_else(|| Error::new(ErrorKind::InvalidOperation, format!("no asset `{path}`")))
+    });
+    env.add_function("abs_url", move |path: &str| {
+        format!("{base_url}/{}", path.trim_start_matches('/'))
+    });
+    env.add_function("get_page", |state: &State, path: &str| {
+        root(state)?
+            .find_page(path.trim_matches('/'))
+            .ok_or_else(|| Error::new(ErrorKind::InvalidOperation, format!("no page `{path}`")))
+    });
+    env.add_function("get_section", |state: &State, path: &str| {
+        root(state)?
+            .find_section(path.trim_matches('/'))
+            .map(Value::from_dyn_object)
+            .ok_or_else(|| Error::new(ErrorKind::InvalidOperation, format!("no section `{path}`")))
+    });
+    env.add_function(
+        "resize_image",
+        move |path: &str, width: u32, format: Option<&str>| {
+            let source = images.find(path).ok_or_else(|| {
+                Error::new(ErrorKind::InvalidOperation, format!("no image `{path}`"))
+            })?;
+            images
+                .resize(&source, width, format)
+                .map(Value::from_serialize)
+                .map_err(|err| {
+                    Error::new(
+                        ErrorKind::InvalidOperation,
+                        format!("failed to resize `{path}`: {err}"),
+                    )
+                })
+        },
+    );
+    env.add_function("load_data", |path: &str| match data::load_file(path) {
+        Ok(Some(value)) => Ok(value),
+        Ok(None) => Err(Error::new(
+            ErrorKind::InvalidOperation,
+            format!("unknown data format for `{path}`"),
+        )),
+        Err(err) => Err(Error::new(
+            ErrorKind::InvalidOperation,
+            format!("failed to load `{path}`: {err}"),
+        )),
+    });
+
+    env.add_filter("markdown", move |text: &str| {
+        let mut html = String::new();
+        pulldown_cmark::html::push_html(&mut html, Parser::new_ext(text, options));
+        Value::from_safe_string(html)
+    });
+    env.add_filter("date", date);
+    env.add_test("date", |value: Value| parse_date(&value).is_some());
+    env.add_filter("slugify", |text: &str| slug::slugify(text));
+    env.add_filter("truncate_words", truncate_words);
+}
+
+fn unknown_language(lang: &str) -> Error {
+    Error::new(
+        ErrorKind::InvalidOperation,
+        format!("unknown language `{lang}`"),
+    )
+}
+
+/// the root of the site from the page being rendered
+fn root(state: &State) -> Result<Arc<Section>, Error> {
+    state
+        .lookup("root")
+        .and_then(|root| root.downcast_object::<Section>())
+        .ok_or_else(|| {
+            Error::new(
+                ErrorKind::InvalidOperation,
+                "the site is only available when rendering a page",
+            )
+        })
+}
+
+/// the path part of the base url, so the site can be served from a subdirectory
+pub(crate) fn url_prefix(base_url: &str) -> String {
+    let path = match base_url.split_once("://") {
+        Some((_, rest)) => rest.find('/').map(|i| &rest[i..]).unwrap_or_default(),
+        None => base_url,
+    };
+    let path = path.trim_matches('/');
+    if path.is_empty() {
+        String::new()
+    } else {
+        format!("/{path}")
+    }
+}
+
+/// the url of the generated file for a path in the content
+fn url_for(prefix: &str, path: &str) -> String {
+    let path = Path::new(path.trim_matches('/'));
+    let url = match path.extension().and_then(|e| e.to_str()) {
+        Some("md") if path.file_stem().and_then(|s| s.to_str()) == Some("_index") => {
+            path.with_file_name("index.html")
+        }
+        Some("md") => path.with_extension("html"),
+        Some(_) => path.into(),
+        None => path.join("index.html"),
+    };
+    format!("{prefix}/{}", url.to_string_lossy())
+}
+
+/// format a date with a strftime style format, defaulting to `%Y-%m-%d`
+fn date(value: Value, format: Option<&str>) -> Result<String, Error> {
+    let format = format.unwrap_or("%Y-%m-%d");
+    let parsed = parse_date(&value)
+        .ok_or_else(|| Error::new(ErrorKind::InvalidOperation, format!("not a date `{value}`")))?;
+    let mut formatted = String::new();
+    write!(formatted, "{}", parsed.format(format)).map_err(|_| {
+        Error::new(
+            ErrorKind::InvalidOperation,
+            format!("bad date format `{format}`"),
+        )
+    })?;
+    Ok(formatted)
+}
+
+/// the date from a value, dates without a timezone are taken to be in utc
+fn parse_date(value: &Value) -> Option<DateTime<FixedOffset>> {
+    let text = value.to_string();
+    DateTime::parse_from_rfc3339(&text).ok().or_else(|| {
+        NaiveDateTime::parse_from_str(&text, "%Y-%m-%dT%H:%M:%S")
+            .or_else(|_| NaiveDateTime::parse_from_str(&text, "%Y-%m-%d %H:%M:%S"))
+            .or_else(|_| NaiveDate::parse_from_str(&text, "%Y-%m-%d").map(|d| d.into()))
+            .ok()
+            .map(|d| d.and_utc().fixed_offset())
+    })
+}
+
+/// keep the first `count` words of the text, marking where it was cut off
+fn truncate_words(text: &str, count: usize, end: Option<&str>) -> String {
+    let mut words = text.split_whitespace();
+    let kept: Vec<_> = words.by_ref().take(count).collect();
+    let mut result = kept.join(" ");
+    if words.next().is_some() {
+        result.push_str(end.unwrap_or("..."));
+    }
+    result
+}
@@ -0,0 +1,210 @@
+/// resizing the images of the site, for the `resize_image` template function and the `srcset` of
+/// the images in the markdown
+///
+/// the resized images are saved to `processed_images` in the site, named with a hash of the source
+/// and the settings, and are kept in the same directory of the work dir so they are only made once
+/// webp images are lossless so the default is jpeg, and avif needs the `avif` feature
+use std::{
+    fmt::Write,
+    fs,
+    io::Cursor,
+    path::{Path, PathBuf},
+};
+
+use image::{codecs::jpeg::JpegEncoder, imageops::FilterType, DynamicImage, ImageFormat};
+use minijinja::HtmlEscape;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+use crate::{
+    config::{Config, ConfigImages},
+    error::{Error, Result},
+    functions,
+};
+
+/// the directory in the site and the work dir the resized images are saved to
+pub const IMAGE_DIR: &str = "processed_images";
+/// the number of hex characters of the hash put in the file names
+const HASH_LEN: usize = 8;
+
+/// a resized image written to the site
+#[derive(Debug, Clone, Serialize)]
+pub struct ResizedImage {
+    pub url: String,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// where to find the images and where to put the resized ones
+#[derive(Debug)]
+pub struct Images {
+    content: PathBuf,
+    assets: PathBuf,
+    /// the name of the assets directory as it appears in urls
+    assets_url: String,
+    out_dir: PathBuf,
+    cache_dir: PathBuf,
+    url_prefix: String,
+    config: ConfigImages,
+}
+
+impl Images {
+    pub fn new(config: &Config) -> Self {
+        let structure = &config.structure;
+        Self {
+            content: PathBuf::from(&structure.content),
+            assets: PathBuf::from(&structure.assets),
+            assets_url: structure.assets.trim_matches('/').to_string(),
+            out_dir: Path::new(&structure.site).join(IMAGE_DIR),
+            cache_dir: Path::new(&structure.work).join(IMAGE_DIR),
+            url_prefix: functions::url_prefix(&config.site.base_url),
+            config: config.images.clone(),
+        }
+    }
+
+    /// the image for a path given to a template, looked for in the assets and then the content
+    pub fn find(&self, path: &str) -> Option<PathBuf> {
+        let path = path.trim_start_matches('/');
+        [self.assets.join(path), self.content.join(path)]
+            .into_iter()
+            .find(|p| p.is_file())
+    }
+
+    /// the image for the `src` of an image in a page in `page_dir`, external images aren't found
+    pub fn resolve(&self, src: &str, page_dir: &Path) -> Option<PathBuf> {
+        if src.contains("://") || src.starts_with("data:") {
+            return None;
+        }
+        let path = match src.strip_prefix('/') {
+            Some(rest) => match rest.strip_prefix(&format!("{}/", self.assets_url)) {
+                Some(asset) => self.assets.join(asset),
+                None => self.content.join(rest),
+            },
+            None => page_dir.join(src),
+        };
+        path.is_file().then_some(path)
+    }
+
+    /// resize the image to `width`, keeping its aspect ratio, images are never made larger
+    pub fn resize(&self, source: &Path, width: u32, format: Option<&str>) -> Result<ResizedImage> {
+        let format = format.unwrap_or(&self.config.format);
+        let (image_format, ext) = image_format(format)?;
+        let contents = fs::read(source)?;
+        let mut hasher = Sha256::new();
+        hasher.update(&contents);
+        hasher.update(format!("{width} {format} {}", self.config.quality));
+        let hash: String = hasher.finalize().iter().fold(String::new(), |mut s, b| {
+            let _ = write!(s, "{b:02x}");
+            s
+        });
+        let stem = source
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or("image");
+        let name = format!("{stem}.{}.{width}.{ext}", &hash[..HASH_LEN]);
+
+        let cached = self.cache_dir.join(&name);
+        if !cached.is_file() {
+            let image = image::load_from_memory(&contents)?;
+            let image = if width < image.width() {
+                image.resize(width, u32::MAX, FilterType::Lanczos3)
+            } else {
+                image
+            };
+            let encoded = self.encode(image, image_format)?;
+            fs::create_dir_all(&self.cache_dir)?;
+            // pages are rendered in parallel so write somewhere else first in case another page
+            // is making the same image
+            let partial = cached.with_extension(format!("{ext}.{:?}", std::thread::current().id()));
+            fs::write(&partial, encoded)?;
+            fs::rename(&partial, &cached)?;
+        }
+        let size = imagesize::size(&cached).map_err(|e| Error::ImageError(e.to_string()))?;
+        let out = self.out_dir.join(&name);
+        // the name holds the hash of the image, so one of the same length is the same image
+        let copied = match (fs::metadata(&cached), fs::metadata(&out)) {
+            (Ok(cached), Ok(out)) => cached.len() == out.len(),
+            _ => false,
+        };
+        if !copied {
+            fs::create_dir_all(&self.out_dir)?;
+            // another page may be copying the same image, so copy next to it and then move it
+            let partial = out.with_extension(format!("{ext}.{:?}", std::thread::current().id()));
+            fs::copy(&cached, &partial)?;
+            fs::rename(&partial, &out)?;
+        }
+        Ok(ResizedImage {
+            url: format!("{}/{IMAGE_DIR}/{name}", self.url_prefix),
+            width: size.width as u32,
+            height: size.height as u32,
+        })
+    }
+
+    fn encode(&self, image: DynamicImage, format: ImageFormat) -> Result<Vec<u8>> {
+        let mut out = Cursor::new(Vec::new());
+        match format {
+            ImageFormat::Jpeg => {
+                let encoder = JpegEncoder::new_with_quality(&mut out, self.config.quality);
+                image.to_rgb8().write_with_encoder(encoder)?;
+            }
+            #[cfg(feature = "avif")]
+            ImageFormat::Avif => {
+                let encoder = image::codecs::avif::AvifEncoder::new_with_speed_quality(
+                    &mut out,
+                    8,
+                    self.config.quality,
+                );
+                image.write_with_encoder(encoder)?;
+            }
+            format => image.write_to(&mut out, format)?,
+        }
+        Ok(out.into_inner())
+    }
+
+    /// whether the image is in a format that can be read to resize it, not svgs
+    pub fn can_resize(source: &Path) -> bool {
+        ImageFormat::from_path(source).is_ok_and(|format| format.reading_enabled())
+    }
+
+    /// the html for an image in the markdown, with a `srcset` of the configured widths smaller
+    /// than the image
+    pub fn img_tag(&self, source: &Path, src: &str, alt: &str, title: &str) -> Result<String> {
+        let size = imagesize::size(source).map_err(|e| Error::ImageError(e.to_string()))?;
+        let (width, height) = (size.width as u32, size.height as u32);
+        let mut srcset = Vec::new();
+        for &w in self.config.widths.iter().filter(|&&w| w < width) {
+            let resized = self.resize(source, w, None)?;
+            srcset.push(format!("{} {}w", resized.url, resized.width));
+        }
+        let mut tag = format!(
+            r#"<img src="{}" alt="{}" width="{width}" height="{height}""#,
+            HtmlEscape(src),
+            HtmlEscape(alt)
+        );
+        if !srcset.is_empty() {
+            srcset.push(format!("{src} {width}w"));
+            let _ = write!(tag, r#" srcset="{}""#, HtmlEscape(&srcset.join(", ")));
+        }
+        if !title.is_empty() {
+            let _ = write!(tag, r#" title="{}""#, HtmlEscape(title));
+        }
+        tag.push_str(" />");
+        Ok(tag)
+    }
+}
+
+/// the image format for its name in the config, along with its extension
+fn image_format(name: &str) -> Result<(ImageFormat, &'static str)> {
+    match name {
+        "webp" => Ok((ImageFormat::WebP, "webp")),
+        "jpeg" | "jpg" => Ok((ImageFormat::Jpeg, "jpg")),
+        "png" => Ok((ImageFormat::Png, "png")),
+        #[cfg(feature = "avif")]
+        "avif" => Ok((ImageFormat::Avif, "avif")),
+        #[cfg(not(feature = "avif"))]
+        "avif" => Err(Error::ImageError(
+            "avif images need sitdown to be built with the `avif` feature".into(),
+        )),
+        _ => Err(Error::ImageError(format!("unknown image format `{name}`"))),
+    }
+}
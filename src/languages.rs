@@ -0,0 +1,149 @@
+/// the languages of a multilingual site
+///
+/// pages are in the default language unless they name another one, like `page.de.md`, or are in
+/// the content directory of a language from the config
+///     [languages.de]
+///     name = "Deutsch"
+///     content = "content_de"
+///     strings = { read_more = "Weiterlesen" }
+/// each language is its own tree of the site, with the pages of the other languages saved under
+/// their code, so `page.de.md` becomes `de/page.html`
+use std::{
+    collections::BTreeMap,
+    ffi::OsStr,
+    path::{Path, PathBuf},
+};
+
+use crate::config::Config;
+
+/// a language of the site and where its pages come from and go to
+#[derive(Debug, Clone)]
+pub struct Language {
+    /// the code of the language, like `de`
+    pub code: String,
+    /// the directory the pages are saved to in the site, empty for the default language
+    pub prefix: PathBuf,
+    /// the content directory of the language, if it has its own
+    pub content: Option<PathBuf>,
+    /// the strings for the `trans` template function by their key
+    pub strings: BTreeMap<String, String>,
+    default: bool,
+    /// the codes of all the languages of the site, to tell them apart from other dots in names
+    codes: Vec<String>,
+}
+
+impl Language {
+    /// the languages of the site, starting with the default language
+    pub fn all(config: &Config) -> Vec<Language> {
+        let default = config.default_language();
+        let mut codes = vec![default.to_string()];
+        codes.extend(config.languages.keys().filter(|c| *c != default).cloned());
+        codes
+            .iter()
+            .map(|code| {
+                let language = config.languages.get(code).cloned().unwrap_or_default();
+                let is_default = code == default;
+                Language {
+                    code: code.clone(),
+                    prefix: if is_default {
+                        PathBuf::new()
+                    } else {
+                        PathBuf::from(code)
+                    },
+                    content: language.content.map(PathBuf::from),
+                    strings: language.strings,
+                    default: is_default,
+                    codes: codes.clone(),
+                }
+            })
+            .collect()
+    }
+
+    /// whether the pages of the language are named in the main content, like `page.de.md`
+    pub fn shares_content(&self) -> bool {
+        !self.default && self.content.is_none()
+    }
+
+    /// the language code in the name of a file, like `de` for `page.de.md`
+    fn code_of<'a>(&self, path: &'a Path) -> Option<&'a str> {
+        let (_, code) = path.file_stem()?.to_str()?.rsplit_once('.')?;
+        self.codes.iter().any(|c| c == code).then_some(code)
+    }
+
+    /// whether a page belongs to this language
+    pub fn includes(&self, path: &Path) -> bool {
+        match self.code_of(path) {
+            Some(code) => code == self.code,
+            None => self.default || self.content.is_some(),
+        }
+    }
+
+    /// the path of the file without the language in its name, `page.de.md` to `page.md`
+    pub fn strip(&self, path: &Path) -> PathBuf {
+        let Some(code) = self.code_of(path) else {
+            return path.into();
+        };
+        let stem = path.file_stem().and_then(OsStr::to_str).unwrap_or_default();
+        let stem = &stem[..stem.len() - code.len() - 1];
+        match path.extension().and_then(OsStr::to_str) {
+            Some(ext) => path.with_file_name(format!("{stem}.{ext}")),
+            None => path.with_file_name(stem),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn language(code: &str, default: bool) -> Language {
+        Language {
+            code: code.into(),
+            prefix: if default { PathBuf::new() } else { code.into() },
+            content: None,
+            strings: BTreeMap::new(),
+            default,
+            codes: vec!["en".into(), "de".into()],
+        }
+    }
+
+    #[test]
+    fn code_in_the_name() {
+        let de = language("de", false);
+        assert_eq!(de.code_of(Path::new("logs/daily.de.md")), Some("de"));
+        assert_eq!(de.code_of(Path::new("logs/_index.de.md")), Some("de"));
+        assert_eq!(de.code_of(Path::new("logs/daily.md")), None);
+        // only the codes of the site's languages count
+        assert_eq!(de.code_of(Path::new("release.v1.md")), None);
+        assert_eq!(de.code_of(Path::new("archive.fr.md")), None);
+    }
+
+    #[test]
+    fn strip_the_code() {
+        let de = language("de", false);
+        assert_eq!(
+            de.strip(Path::new("logs/_index.de.md")),
+            Path::new("logs/_index.md")
+        );
+        assert_eq!(
+            de.strip(Path::new("about.en.html")),
+            Path::new("about.html")
+        );
+        assert_eq!(
+            de.strip(Path::new("release.v1.md")),
+            Path::new("release.v1.md")
+        );
+        assert_eq!(de.strip(Path::new("v2.0.de.md")), Path::new("v2.0.md"));
+    }
+
+    #[test]
+    fn pages_of_each_language() {
+        let (en, de) = (language("en", true), language("de", false));
+        assert!(en.includes(Path::new("page.md")));
+        assert!(en.includes(Path::new("page.en.md")));
+        assert!(!en.includes(Path::new("page.de.md")));
+        assert!(de.includes(Path::new("page.de.md")));
+        assert!(!de.includes(Path::new("page.md")));
+        assert!(de.shares_content() && !en.shares_content());
+    }
+}
@@ -1,6 +1,18 @@
 pub mod app;
+pub mod archetypes;
+pub mod assets;
 pub mod config;
+pub mod data;
+pub mod diagnostic;
 pub mod error;
+pub mod functions;
+pub mod images;
+pub mod languages;
+pub mod minify;
+pub mod report;
+pub mod search;
+pub mod shortcodes;
+pub mod starters;
 pub mod templates;
 pub mod tree;
 pub mod utils;
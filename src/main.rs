@@ -3,9 +3,15 @@ use clap::{Parser, Subcommand};
 use notify::{RecursiveMode, Watcher};
 use notify_debouncer_full::new_debouncer;
 use sitdown::app::App;
-use sitdown::error::Result;
-use sitdown::utils::{create_new, get_config};
+use sitdown::archetypes;
+use sitdown::diagnostic::report;
+use sitdown::error::{self, Error, Result};
+use sitdown::starters::{self, Starter};
+use sitdown::utils::get_config;
 use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process;
 use std::{collections::HashSet, net::SocketAddr, time::Duration};
 use tower_http::{services::ServeDir, trace::TraceLayer};
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
@@ -16,19 +22,40 @@ struct Args {
     /// command
     #[command(subcommand)]
     command: Commands,
+    /// the profile of the config to use, like `production`
+    #[arg(long, global = true)]
+    profile: Option<String>,
 }
 
 #[derive(Subcommand)]
 enum Commands {
-    /// create a new minimal site
+    /// create a new site from a starter
     New {
         /// name of the new site
         name: String,
+        /// the starter to create the site from, `blog`, `book`, `docs`, or `minimal`
+        #[arg(long, default_value = "minimal", conflicts_with = "from")]
+        template: String,
+        /// a local directory or `.tar`/`.tar.gz` archive to create the site from instead
+        #[arg(long)]
+        from: Option<PathBuf>,
+        /// write into the directory even if it isn't empty
+        #[arg(long)]
+        force: bool,
+    },
+    /// create a new page in the content from its archetype
+    Add {
+        /// where to put the page in the content, like `logs/my-new-post.md`
+        path: String,
     },
     /// start the server
     Serve,
     /// generate the files to serve
-    Generate,
+    Generate {
+        /// print the build report as json, for tracking builds over time
+        #[arg(long)]
+        json: bool,
+    },
     /// watch for updates and re-generate site on updates
     Watch,
     /// clean up the generated files
@@ -38,6 +65,24 @@ enum Commands {
 #[tokio::main]
 async fn main() {
     let args = Args::parse();
+    if let Err(err) = run(args).await {
+        let code = err.exit_code();
+        report(err);
+        process::exit(code);
+    }
+}
+
+/// run the command, the exit code comes from what kind of error it fails with
+async fn run(args: Args) -> Result<()> {
+    let profile = args.profile.as_deref();
+    // the warnings from building the site go through `log`, the server uses `tracing`
+    if !matches!(args.command, Commands::Serve) {
+        let level = match args.command {
+            Commands::Watch => "info",
+            _ => "warn",
+        };
+        env_logger::Builder::from_env(env_logger::Env::default().default_filter_or(level)).init();
+    }
     match args.command {
         Commands::Serve => {
             tracing_subscriber::registry()
@@ -47,46 +92,63 @@ async fn main() {
                 )
                 .with(tracing_subscriber::fmt::layer())
                 .init();
-
-            tokio::join!(serve(using_serve_dir(), 3000));
+            serve(3000, profile).await
         }
-        Commands::Generate => {
-            let config = get_config();
-            let app = App::new(&config);
-            app.create().unwrap();
-            // Site::new().run();
-        }
-        Commands::Watch => {
-            env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info"))
-                .init();
-            if let Err(err) = watch() {
-                log::error!("Encountered error `{err:?}`");
+        Commands::Generate { json } => {
+            let config = get_config(profile)?;
+            let summary = App::new(&config)?.create()?;
+            if json {
+                output(format!("{}\n", serde_json::to_string_pretty(&summary)?))
+            } else {
+                output(summary.to_string())
             }
         }
-        Commands::New { name } => {
-            env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info"))
-                .init();
-            if let Err(err) = create_new(name) {
-                log::error!("Encountered error `{err}`");
-            }
+        Commands::Watch => watch(profile),
+        Commands::New {
+            name,
+            template,
+            from,
+            force,
+        } => {
+            let starter = match from {
+                Some(path) => Starter::Path(path),
+                None => Starter::Builtin(template),
+            };
+            starters::create(&name, &starter, force)?;
+            output(format!("Created a new site in `{name}`\n"))
         }
-        Commands::Clean => {
-            let config = get_config();
-            env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info"))
-                .init();
-            if let Err(err) = fs::remove_dir_all(config.structure.site) {
-                log::error!("Encountered error `{err}`");
-            }
+        Commands::Add { path } => {
+            let config = get_config(profile)?;
+            let save = archetypes::add(&config, &path)?;
+            output(format!("Created `{}`\n", save.display()))
+        }
+        Commands::Clean => clean(profile),
+    }
+}
 
-            if let Err(err) = fs::remove_dir_all(config.structure.work) {
-                log::error!("Encountered error `{err}`");
-            }
-        } // _ => println!("lol"),
+/// print to stdout, stopping quietly if it's closed early like when piped into `head`
+fn output(text: String) -> Result<()> {
+    match io::stdout().lock().write_all(text.as_bytes()) {
+        Err(err) if err.kind() != io::ErrorKind::BrokenPipe => Err(err.into()),
+        _ => Ok(()),
     }
 }
 
-fn watch() -> Result<()> {
-    let config = get_config();
+/// remove the generated site and work directories, it's fine if they aren't there
+fn clean(profile: Option<&str>) -> Result<()> {
+    let config = get_config(profile)?;
+    let removed = [&config.structure.site, &config.structure.work]
+        .into_iter()
+        .map(|dir| match fs::remove_dir_all(dir) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => Err(Error::from(err).in_file(dir)),
+            _ => Ok(()),
+        });
+    error::collect(removed)?;
+    Ok(())
+}
+
+fn watch(profile: Option<&str>) -> Result<()> {
+    let config = get_config(profile)?;
     let (tx, rx) = std::sync::mpsc::channel();
 
     let mut debouncer = new_debouncer(Duration::from_secs(2), None, tx)?;
@@ -100,6 +162,28 @@ fn watch() -> Result<()> {
     debouncer
         .watcher()
         .watch(config.structure.content.as_ref(), RecursiveMode::Recursive)?;
+    if Path::new(&config.structure.data).is_dir() {
+        debouncer
+            .watcher()
+            .watch(config.structure.data.as_ref(), RecursiveMode::Recursive)?;
+    }
+    for dir in config.languages.values().filter_map(|l| l.content.as_ref()) {
+        debouncer
+            .watcher()
+            .watch(dir.as_ref(), RecursiveMode::Recursive)?;
+    }
+    for include in &config.assets.sass_include {
+        if Path::new(include).is_dir() {
+            debouncer
+                .watcher()
+                .watch(include.as_ref(), RecursiveMode::Recursive)?;
+        }
+    }
+    if let Some(theme) = config.theme_dir() {
+        debouncer
+            .watcher()
+            .watch(theme.as_ref(), RecursiveMode::Recursive)?;
+    }
 
     for res in rx {
         match res {
@@ -107,26 +191,28 @@ fn watch() -> Result<()> {
                 let updated: HashSet<_> = event.into_iter().flat_map(|e| e.paths.clone()).collect();
                 log::info!("Changes in: {updated:?}");
                 log::info!("Regenerating");
-                App::new(&config).create()?;
-            }
-            Err(error) => {
-                println!("Error received `{error:?}`");
+                // keep watching so the errors can be fixed
+                match App::new(&config).and_then(|app| app.create()) {
+                    Ok(summary) => log::info!("{summary}"),
+                    Err(err) => report(err),
+                }
             }
+            Err(errors) => report(Error::from_errors(errors.into_iter().map(Error::from))),
         }
     }
     Ok(())
 }
 
-fn using_serve_dir() -> Router {
-    let config = get_config();
-    Router::new().nest_service("/", ServeDir::new(config.structure.site))
+fn using_serve_dir(profile: Option<&str>) -> Result<Router> {
+    let config = get_config(profile)?;
+    Ok(Router::new().nest_service("/", ServeDir::new(config.structure.site)))
 }
 
-async fn serve(app: Router, port: u16) {
+async fn serve(port: u16, profile: Option<&str>) -> Result<()> {
+    let app = using_serve_dir(profile)?;
     let addr = SocketAddr::from(([127, 0, 0, 1], port));
-    let listener = tokio::net::TcpListener::bind(addr).await.unwrap();
-    tracing::info!("listening on {}", listener.local_addr().unwrap());
-    axum::serve(listener, app.layer(TraceLayer::new_for_http()))
-        .await
-        .unwrap();
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    tracing::info!("listening on {}", listener.local_addr()?);
+    axum::serve(listener, app.layer(TraceLayer::new_for_http())).await?;
+    Ok(())
 }
@@ -0,0 +1,37 @@
+/// shrinking the html, css, and js written to the site
+///
+/// pages can opt out with `minify: false` in their metadata, and assets with the `minify_skip`
+/// globs of the asset config, files named like `app.min.js` are taken as already minified
+use minify_html::Cfg;
+
+/// minify a rendered page, along with the css and js inside it
+pub fn html(text: &str) -> String {
+    let mut cfg = Cfg::new();
+    cfg.minify_css = true;
+    cfg.minify_js = true;
+    cfg.keep_closing_tags = true;
+    cfg.keep_html_and_head_opening_tags = true;
+    String::from_utf8_lossy(&minify_html::minify(text.as_bytes(), &cfg)).into_owned()
+}
+
+/// minify a css or js asset, other files and files that fail to minify are kept as they are
+pub fn asset(name: &str, contents: Vec<u8>) -> Vec<u8> {
+    if name.contains(".min.") {
+        return contents;
+    }
+    let Ok(text) = std::str::from_utf8(&contents) else {
+        return contents;
+    };
+    let minified = match name.rsplit_once('.').map(|(_, ext)| ext) {
+        Some("css") => minifier::css::minify(text).map(|m| m.to_string()),
+        Some("js") => minifier::js::minify(text).map(|m| m.to_string()),
+        _ => return contents,
+    };
+    match minified {
+        Ok(minified) => minified.into_bytes(),
+        Err(err) => {
+            log::warn!("Failed to minify `{name}` with `{err}`, keeping it as it is");
+            contents
+        }
+    }
+}
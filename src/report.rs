@@ -0,0 +1,133 @@
+/// the summary of a build of the site, with how long each part of it took
+///
+/// printed at the end of `sitdown generate`, or as json with `--json`
+use std::{collections::HashMap, fmt, path::PathBuf, time::Duration};
+
+use serde::Serialize;
+
+/// how many of the slowest pages and templates to show
+const SLOWEST: usize = 5;
+
+/// the time taken to render a file of the site
+#[derive(Debug, Clone)]
+pub struct RenderTime {
+    /// location of the file in the site
+    pub save: PathBuf,
+    /// the template the file was rendered with
+    pub template: String,
+    pub time: Duration,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct Report {
+    /// the number of pages rendered, including the index pages and feeds of the directories
+    pub pages: usize,
+    /// the number of files copied from the asset directories
+    pub assets: usize,
+    /// the number of pages marked as drafts that were left out
+    pub drafts: usize,
+    /// the time for the whole build in milliseconds
+    pub total_ms: f64,
+    /// how long each phase of the build took, in the order they happened
+    pub phases: Vec<Phase>,
+    pub slowest_pages: Vec<PageTime>,
+    /// the templates taking the longest over all the pages using them
+    pub slowest_templates: Vec<TemplateTime>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Phase {
+    pub name: &'static str,
+    pub ms: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PageTime {
+    pub path: PathBuf,
+    pub template: String,
+    pub ms: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TemplateTime {
+    pub template: String,
+    /// the number of pages rendered with the template
+    pub pages: usize,
+    pub ms: f64,
+}
+
+impl Report {
+    /// record how long a phase of the build took
+    pub fn phase(&mut self, name: &'static str, time: Duration) {
+        self.total_ms += ms(time);
+        self.phases.push(Phase { name, ms: ms(time) });
+    }
+
+    /// fill in the pages and templates from the times taken to render them
+    pub fn rendered(&mut self, mut times: Vec<RenderTime>) {
+        self.pages = times.len();
+        let mut templates: HashMap<String, (usize, Duration)> = HashMap::new();
+        for time in &times {
+            let entry = templates.entry(time.template.clone()).or_default();
+            entry.0 += 1;
+            entry.1 += time.time;
+        }
+        let mut templates: Vec<_> = templates.into_iter().collect();
+        templates.sort_by(|a, b| b.1 .1.cmp(&a.1 .1).then_with(|| a.0.cmp(&b.0)));
+        self.slowest_templates = templates
+            .into_iter()
+            .take(SLOWEST)
+            .map(|(template, (pages, time))| TemplateTime {
+                template,
+                pages,
+                ms: ms(time),
+            })
+            .collect();
+        times.sort_by(|a, b| b.time.cmp(&a.time).then_with(|| a.save.cmp(&b.save)));
+        self.slowest_pages = times
+            .into_iter()
+            .take(SLOWEST)
+            .map(|time| PageTime {
+                path: time.save,
+                template: time.template,
+                ms: ms(time.time),
+            })
+            .collect();
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Built {} pages and copied {} assets in {:.1}ms",
+            self.pages, self.assets, self.total_ms
+        )?;
+        if self.drafts > 0 {
+            write!(f, ", leaving out {} drafts", self.drafts)?;
+        }
+        writeln!(f)?;
+        for phase in &self.phases {
+            writeln!(f, "  {:<16}{:>10.1}ms", phase.name, phase.ms)?;
+        }
+        if !self.slowest_pages.is_empty() {
+            writeln!(f, "slowest pages:")?;
+            for page in &self.slowest_pages {
+                let name = format!("{} ({})", page.path.display(), page.template);
+                writeln!(f, "  {name:<40}{:>10.1}ms", page.ms)?;
+            }
+        }
+        if !self.slowest_templates.is_empty() {
+            writeln!(f, "slowest templates:")?;
+            for template in &self.slowest_templates {
+                let name = format!("{} ({} pages)", template.template, template.pages);
+                writeln!(f, "  {name:<40}{:>10.1}ms", template.ms)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn ms(time: Duration) -> f64 {
+    time.as_secs_f64() * 1000.0
+}
@@ -0,0 +1,245 @@
+/// the search index of the site, a json list of the pages for a search box in the browser
+///
+/// each entry has the fields from the search config, taken from the pages before they are
+/// rendered with their templates
+///     {"title": "Daily", "url": "/logs/daily.html", "headings": ["Monday"], "body": "...", "tags": []}
+/// pages can be left out with `search: false` in their metadata
+use std::{fs, path::Path, sync::Arc};
+
+use serde_json::{Map, Value};
+
+use crate::{
+    config::{ConfigSearch, SearchField},
+    error::Result,
+    tree::{PageInfo, Section},
+};
+
+/// elements whose text isn't part of the page
+const HIDDEN: [&str; 3] = ["script", "style", "template"];
+/// elements within a line of text, which don't separate words
+const INLINE: [&str; 13] = [
+    "a", "abbr", "b", "code", "del", "em", "i", "mark", "small", "span", "strong", "sub", "sup",
+];
+
+/// write the search index for the pages of the site in all its languages to the output directory
+pub fn write_index<T: AsRef<Path>>(
+    roots: &[Arc<Section>],
+    config: &ConfigSearch,
+    url_prefix: &str,
+    out_dir: T,
+) -> Result<()> {
+    let mut entries = Vec::new();
+    for root in roots {
+        collect(root, config, url_prefix, &mut entries);
+    }
+    fs::write(
+        out_dir.as_ref().join(&config.file),
+        serde_json::to_string(&entries)?,
+    )?;
+    Ok(())
+}
+
+fn collect(section: &Section, config: &ConfigSearch, url_prefix: &str, entries: &mut Vec<Value>) {
+    for page in section.pages() {
+        if indexed(page, config) {
+            entries.push(entry(page, config, url_prefix));
+        }
+    }
+    for dir in section.dirs() {
+        collect(dir, config, url_prefix, entries);
+    }
+}
+
+/// whether the page is in one of the indexed sections and hasn't opted out, the sections are
+/// the same for every language
+fn indexed(page: &PageInfo, config: &ConfigSearch) -> bool {
+    let wanted = config.sections.is_empty()
+        || config
+            .sections
+            .iter()
+            .any(|section| page.source().starts_with(section.trim_matches('/')));
+    wanted && page.get("search").and_then(|v| v.as_bool()) != Some(false)
+}
+
+fn entry(page: &PageInfo, config: &ConfigSearch, url_prefix: &str) -> Value {
+    let (text, headings) = text_of(page.html());
+    let mut entry = Map::new();
+    for field in &config.fields {
+        let value = match field {
+            SearchField::Title => Value::from(page.title()),
+            SearchField::Url => {
+                let path: Vec<_> = page
+                    .save()
+                    .components()
+                    .map(|c| c.as_os_str().to_string_lossy())
+                    .collect();
+                Value::from(format!("{url_prefix}/{}", path.join("/")))
+            }
+            SearchField::Headings => Value::from(headings.clone()),
+            SearchField::Body => Value::from(truncate(&text, config.max_body)),
+            SearchField::Tags => tags(page),
+            SearchField::Lang => Value::from(page.lang()),
+        };
+        entry.insert(field_name(*field).into(), value);
+    }
+    Value::Object(entry)
+}
+
+fn field_name(field: SearchField) -> &'static str {
+    match field {
+        SearchField::Title => "title",
+        SearchField::Url => "url",
+        SearchField::Headings => "headings",
+        SearchField::Body => "body",
+        SearchField::Tags => "tags",
+        SearchField::Lang => "lang",
+    }
+}
+
+/// the tags of the page as a list, a single tag can be given as a string
+fn tags(page: &PageInfo) -> Value {
+    match page.get("tags") {
+        Some(serde_yml::Value::String(tag)) => Value::from(vec![tag.clone()]),
+        Some(tags @ serde_yml::Value::Sequence(_)) => {
+            serde_json::to_value(tags).unwrap_or_else(|_| Value::Array(Vec::new()))
+        }
+        _ => Value::Array(Vec::new()),
+    }
+}
+
+/// the plain text of the html, along with the text of its headings
+fn text_of(html: &str) -> (String, Vec<String>) {
+    let mut text = String::new();
+    let mut headings = Vec::new();
+    let mut heading: Option<String> = None;
+    let mut hidden: Option<&str> = None;
+    let mut rest = html;
+    while !rest.is_empty() {
+        let Some(start) = rest.find('<') else {
+            push_text(rest, &mut text, &mut heading, hidden);
+            break;
+        };
+        push_text(&rest[..start], &mut text, &mut heading, hidden);
+        let Some(len) = rest[start..].find('>') else {
+            break;
+        };
+        let tag = &rest[start + 1..start + len];
+        let closing = tag.starts_with('/');
+        let name: String = tag
+            .trim_start_matches('/')
+            .chars()
+            .take_while(char::is_ascii_alphanumeric)
+            .collect::<String>()
+            .to_ascii_lowercase();
+        match hidden {
+            Some(element) if closing && name == element => hidden = None,
+            Some(_) => {}
+            None if !closing => {
+                hidden = HIDDEN.into_iter().find(|&element| element == name);
+            }
+            None => {}
+        }
+        if is_heading(&name) {
+            if closing {
+                if let Some(h) = heading.take() {
+                    let h = collapse(&h);
+                    if !h.is_empty() {
+                        headings.push(h);
+                    }
+                }
+            } else {
+                heading = Some(String::new());
+            }
+        }
+        // keep words either side of a block apart
+        if !INLINE.contains(&name.as_str()) {
+            text.push(' ');
+        }
+        rest = &rest[start + len + 1..];
+    }
+    (collapse(&text), headings)
+}
+
+fn push_text(part: &str, text: &mut String, heading: &mut Option<String>, hidden: Option<&str>) {
+    if hidden.is_some() {
+        return;
+    }
+    let part = unescape(part);
+    if let Some(heading) = heading {
+        heading.push_str(&part);
+    }
+    text.push_str(&part);
+}
+
+fn is_heading(name: &str) -> bool {
+    matches!(name, "h1" | "h2" | "h3" | "h4" | "h5" | "h6")
+}
+
+/// undo the escaping of the html, for the entities markdown produces
+fn unescape(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&nbsp;", " ")
+        .replace("&amp;", "&")
+}
+
+/// join the words with single spaces
+fn collapse(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// cut the text to at most `max` characters, at the end of a word if there is one
+fn truncate(text: &str, max: usize) -> String {
+    if max == 0 || text.chars().count() <= max {
+        return text.to_string();
+    }
+    let end = text.char_indices().nth(max).map_or(text.len(), |(i, _)| i);
+    let cut = &text[..end];
+    match cut.rfind(' ') {
+        Some(space) if space > 0 => cut[..space].to_string(),
+        _ => cut.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn text_and_headings() {
+        let (text, headings) =
+            text_of("<h2 id=\"a\">Getting <em>started</em></h2>\n<p>Install it &amp; run.</p>");
+        assert_eq!(text, "Getting started Install it & run.");
+        assert_eq!(headings, ["Getting started"]);
+    }
+
+    #[test]
+    fn inline_tags_keep_words_together() {
+        let (text, _) = text_of("<p>un<strong>break</strong>able</p><p>next</p>");
+        assert_eq!(text, "unbreakable next");
+    }
+
+    #[test]
+    fn hidden_elements_are_left_out() {
+        let html = "<p>shown</p><script>let x = '<p>';</script><style>p { }</style><p>also</p>";
+        let (text, headings) = text_of(html);
+        assert_eq!(text, "shown also");
+        assert!(headings.is_empty());
+    }
+
+    #[test]
+    fn truncate_at_a_word() {
+        assert_eq!(truncate("one two three", 9), "one two");
+        assert_eq!(truncate("one two three", 0), "one two three");
+        assert_eq!(truncate("one two", 20), "one two");
+        assert_eq!(truncate("unbroken", 4), "unbr");
+    }
+
+    #[test]
+    fn truncate_multi_byte_text() {
+        assert_eq!(truncate("héllo wörld", 8), "héllo");
+        assert_eq!(truncate("日本語のテキスト", 3), "日本語");
+    }
+}
@@ -0,0 +1,357 @@
+/// shortcodes are small named templates that can be called from within the markdown content
+///
+/// the inline form passes only arguments to the template
+///     {{< figure src="/assets/cat.png" caption="A cat" >}}
+/// the block form also passes the markdown between the tags, rendered to html, as `inner`
+///     {{< note kind="warning" >}} Some *markdown* {{< /note >}}
+///
+/// the templates are looked up by name from the `shortcodes` directory of the templates
+/// shortcodes in code and the front matter are left as they are, and `{{</* note */>}}` is
+/// written out as `{{< note >}}` for showing a shortcode without calling it
+///
+/// pages with `render_content` are rendered through the templates after the shortcodes are
+/// expanded, so their output is wrapped in `{% raw %}` to keep it as it is
+use std::{collections::BTreeMap, ops::Range};
+
+use minijinja::{Environment, ErrorKind, Value};
+use pulldown_cmark::{Event, Options, Parser};
+
+use crate::{
+    error::{Error, Result},
+    templates::SHORTCODE_DIR,
+};
+
+const OPEN: &str = "{{<";
+const CLOSE: &str = ">}}";
+const ESCAPED_OPEN: &str = "{{</*";
+const ESCAPED_CLOSE: &str = "*/>}}";
+
+/// replace all the shortcodes in the text with their rendered templates, `raw` keeps what they
+/// write out from being rendered again when the text goes through the templates
+pub fn expand(text: &str, templates: &Environment, options: &Options, raw: bool) -> Result<String> {
+    let source = Source::new(text, options);
+    expand_within(&source, text, templates, options, raw)
+}
+
+/// the text to write out, wrapped so the templates leave it alone if asked for
+fn literal(text: &str, raw: bool) -> String {
+    if raw {
+        format!("{{% raw %}}{text}{{% endraw %}}")
+    } else {
+        text.to_string()
+    }
+}
+
+/// the whole text the shortcodes are expanded in, with where its code is
+struct Source<'a> {
+    text: &'a str,
+    /// the code blocks, inline code, and front matter, which shortcodes are left alone in
+    code: Vec<Range<usize>>,
+}
+
+impl<'a> Source<'a> {
+    fn new(text: &'a str, options: &Options) -> Self {
+        let code = Parser::new_ext(text, *options)
+            .into_offset_iter()
+            .filter_map(|(event, range)| match event {
+                Event::Start(
+                    pulldown_cmark::Tag::CodeBlock(_) | pulldown_cmark::Tag::MetadataBlock(_),
+                )
+                | Event::Code(_) => Some(range),
+                _ => None,
+            })
+            .collect();
+        Self { text, code }
+    }
+
+    /// the offset of a part of the source within the source
+    fn offset(&self, part: &str) -> usize {
+        part.as_ptr() as usize - self.text.as_ptr() as usize
+    }
+
+    /// whether the part of the source starts in code
+    fn in_code(&self, part: &str) -> bool {
+        let at = self.offset(part);
+        self.code.iter().any(|range| range.contains(&at))
+    }
+}
+
+/// replace the shortcodes in a part of the source, errors are marked with where they are in the
+/// whole source
+fn expand_within(
+    source: &Source,
+    text: &str,
+    templates: &Environment,
+    options: &Options,
+    raw: bool,
+) -> Result<String> {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find(OPEN) {
+        out.push_str(&rest[..start]);
+        let here = &rest[start..];
+        let at = source.offset(here);
+        if source.in_code(here) {
+            out.push_str(&literal(OPEN, raw));
+            rest = &here[OPEN.len()..];
+            continue;
+        }
+        if let Some(escaped) = here.strip_prefix(ESCAPED_OPEN) {
+            let end = escaped.find(ESCAPED_CLOSE).ok_or_else(|| {
+                Error::ShortcodeError(format!("unclosed tag `{}`", first_line(here))).at_offset(at)
+            })?;
+            out.push_str(&literal(&format!("{OPEN}{}{CLOSE}", &escaped[..end]), raw));
+            rest = &escaped[end + ESCAPED_CLOSE.len()..];
+            continue;
+        }
+        let tag = Tag::parse(here).map_err(|e| e.at_offset(at))?;
+        let after = &rest[start + tag.len..];
+        if tag.closing {
+            return Err(Error::ShortcodeError(format!(
+                "closing tag for `{}` without an opening tag",
+                tag.name
+            ))
+            .at_offset(at));
+        }
+        let rendered = |inner| {
+            tag.render(templates, inner).map_err(|e| match e {
+                Error::ShortcodeError(_) => e.at_offset(at),
+                e => e.note(format!("in the shortcode `{}`", tag.name)),
+            })
+        };
+        match find_closing(source, after, &tag.name).map_err(|e| e.at_offset(at))? {
+            Some((inner_end, close_len)) => {
+                // the whole block is kept as it is, and raw blocks can't be nested
+                let inner = expand_within(source, &after[..inner_end], templates, options, false)?;
+                let mut html = String::new();
+                pulldown_cmark::html::push_html(&mut html, Parser::new_ext(&inner, *options));
+                out.push_str(&literal(&rendered(Some(html))?, raw));
+                rest = &after[inner_end + close_len..];
+            }
+            None => {
+                out.push_str(&literal(&rendered(None)?, raw));
+                rest = after;
+            }
+        }
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+/// find the closing tag matching `name`, accounting for nested blocks of the same name
+/// gives the offset of the closing tag and its length
+fn find_closing(source: &Source, text: &str, name: &str) -> Result<Option<(usize, usize)>> {
+    let mut depth = 0;
+    let mut offset = 0;
+    while let Some(start) = text[offset..].find(OPEN) {
+        let start = offset + start;
+        let here = &text[start..];
+        if source.in_code(here) || here.starts_with(ESCAPED_OPEN) {
+            offset = start + OPEN.len();
+            continue;
+        }
+        let tag = Tag::parse(here)?;
+        if tag.name == name {
+            if tag.closing && depth == 0 {
+                return Ok(Some((start, tag.len)));
+            } else if tag.closing {
+                depth -= 1;
+            } else {
+                depth += 1;
+            }
+        }
+        offset = start + tag.len;
+    }
+    Ok(None)
+}
+
+/// a single `{{< ... >}}` tag
+struct Tag {
+    /// the name of the shortcode template
+    name: String,
+    /// whether it is the `{{< /name >}}` form
+    closing: bool,
+    /// the arguments given to the shortcode
+    args: BTreeMap<String, Value>,
+    /// the length of the tag in the source text
+    len: usize,
+}
+
+impl Tag {
+    /// parse the tag at the start of the text
+    fn parse(text: &str) -> Result<Self> {
+        let end = text
+            .find(CLOSE)
+            .ok_or_else(|| Error::ShortcodeError(format!("unclosed tag `{}`", first_line(text))))?;
+        let body = text[OPEN.len()..end].trim();
+        let (closing, body) = match body.strip_prefix('/') {
+            Some(body) => (true, body.trim_start()),
+            None => (false, body),
+        };
+        let name_end = body.find(char::is_whitespace).unwrap_or(body.len());
+        let name = body[..name_end].to_string();
+        if name.is_empty() {
+            return Err(Error::ShortcodeError(format!(
+                "missing name in `{}`",
+                &text[..end + CLOSE.len()]
+            )));
+        }
+        let args = parse_args(&body[name_end..]).ok_or_else(|| {
+            Error::ShortcodeError(format!("bad arguments in `{}`", &text[..end + CLOSE.len()]))
+        })?;
+        Ok(Self {
+            name,
+            closing,
+            args,
+            len: end + CLOSE.len(),
+        })
+    }
+
+    fn render(&self, templates: &Environment, inner: Option<String>) -> Result<String> {
+        let template = templates
+            .get_template(&format!("{SHORTCODE_DIR}/{}", self.name))
+            .map_err(|err| match err.kind() {
+                ErrorKind::TemplateNotFound => {
+                    Error::ShortcodeError(format!("unknown shortcode `{}`", self.name))
+                }
+                _ => err.into(),
+            })?;
+        let mut context = self.args.clone();
+        if let Some(inner) = inner {
+            context.insert("inner".into(), Value::from_safe_string(inner));
+        }
+        Ok(template.render(context)?)
+    }
+}
+
+/// parse `key="value" other=3` style arguments
+/// quoted values are strings, unquoted values are parsed as numbers or booleans if possible
+fn parse_args(text: &str) -> Option<BTreeMap<String, Value>> {
+    let mut args = BTreeMap::new();
+    let mut rest = text.trim_start();
+    while !rest.is_empty() {
+        let eq = rest.find('=')?;
+        let key = rest[..eq].trim();
+        if key.is_empty() || key.contains(char::is_whitespace) {
+            return None;
+        }
+        rest = rest[eq + 1..].trim_start();
+        let value = if let Some(quoted) = rest.strip_prefix('"') {
+            let end = quoted.find('"')?;
+            let value = Value::from(&quoted[..end]);
+            rest = &quoted[end + 1..];
+            value
+        } else {
+            let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+            let value = bare_value(&rest[..end]);
+            rest = &rest[end..];
+            value
+        };
+        args.insert(key.to_string(), value);
+        rest = rest.trim_start();
+    }
+    Some(args)
+}
+
+fn bare_value(text: &str) -> Value {
+    if let Ok(b) = text.parse::<bool>() {
+        Value::from(b)
+    } else if let Ok(i) = text.parse::<i64>() {
+        Value::from(i)
+    } else if let Ok(f) = text.parse::<f64>() {
+        Value::from(f)
+    } else {
+        Value::from(text)
+    }
+}
+
+fn first_line(text: &str) -> &str {
+    text.lines().next().unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn source(text: &str) -> Source<'_> {
+        Source::new(text, &Options::empty())
+    }
+
+    #[test]
+    fn args_of_each_kind() {
+        let args =
+            parse_args(r#" src="/a b.png" width=300 ratio=1.5 lazy=true kind=note "#).unwrap();
+        assert_eq!(args["src"], Value::from("/a b.png"));
+        assert_eq!(args["width"], Value::from(300));
+        assert_eq!(args["ratio"], Value::from(1.5));
+        assert_eq!(args["lazy"], Value::from(true));
+        assert_eq!(args["kind"], Value::from("note"));
+    }
+
+    #[test]
+    fn quoted_args_keep_equals_signs() {
+        let args = parse_args(r#"query="a=b&c=d" other=1"#).unwrap();
+        assert_eq!(args["query"], Value::from("a=b&c=d"));
+        assert_eq!(args["other"], Value::from(1));
+    }
+
+    #[test]
+    fn bad_args() {
+        assert!(parse_args("novalue").is_none());
+        assert!(parse_args(r#"open="never closed"#).is_none());
+        assert!(parse_args(r#"two words="x""#).is_none());
+        assert!(parse_args("").unwrap().is_empty());
+    }
+
+    #[test]
+    fn closing_tag_after_nested_blocks() {
+        let text = "a {{< note >}} b {{< /note >}} c {{< /note >}} d";
+        let (end, len) = find_closing(&source(text), text, "note").unwrap().unwrap();
+        assert_eq!(&text[end..end + len], "{{< /note >}}");
+        assert_eq!(&text[end + len..], " d");
+    }
+
+    #[test]
+    fn closing_tag_of_other_shortcodes_is_skipped() {
+        let text = "{{< figure src=\"x\" >}} {{< /aside >}} {{< /note >}}";
+        let (end, _) = find_closing(&source(text), text, "note").unwrap().unwrap();
+        assert_eq!(&text[end..], "{{< /note >}}");
+    }
+
+    #[test]
+    fn inline_shortcode_has_no_closing_tag() {
+        let text = " and then {{< figure >}}";
+        assert!(find_closing(&source(text), text, "note").unwrap().is_none());
+    }
+
+    #[test]
+    fn closing_tags_in_code_are_skipped() {
+        let text = "`{{< /note >}}` {{</* /note */>}} {{< /note >}}";
+        let (end, _) = find_closing(&source(text), text, "note").unwrap().unwrap();
+        assert_eq!(&text[end..], "{{< /note >}}");
+    }
+
+    #[test]
+    fn escaped_and_code_shortcodes_are_left() {
+        let text = "{{</* note kind=\"x\" */>}}\n\n```\n{{< note >}}\n```\n";
+        let expanded = expand(text, &Environment::new(), &Options::empty(), false).unwrap();
+        assert_eq!(
+            expanded,
+            "{{< note kind=\"x\" >}}\n\n```\n{{< note >}}\n```\n"
+        );
+    }
+
+    #[test]
+    fn shortcodes_are_kept_through_the_templates() {
+        let mut env = Environment::new();
+        env.add_template("shortcodes/note", "<div>{{ inner }}{{ '{{ x }}' }}</div>")
+            .unwrap();
+        let text = "{{ 1 + 1 }} {{</* note */>}} {{< note >}}hi{{< /note >}} `{{< note >}}`";
+        let expanded = expand(text, &env, &Options::empty(), true).unwrap();
+        let rendered = env.render_str(&expanded, ()).unwrap();
+        assert_eq!(
+            rendered,
+            "2 {{< note >}} <div><p>hi</p>\n{{ x }}</div> `{{< note >}}`"
+        );
+    }
+}
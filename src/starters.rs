@@ -0,0 +1,288 @@
+/// the starter sites that `sitdown new` creates a site from
+///
+/// the built in starters are the directories of `starters` in the repository, included in the
+/// binary, a site can also be started from a local directory or a `.tar` or `.tar.gz` archive of
+/// one, archives with everything inside a single directory have it taken off, like the archives
+/// of a repository
+use std::{
+    fs::{self, File},
+    io::Read,
+    path::{Component, Path, PathBuf},
+};
+
+use flate2::read::GzDecoder;
+use include_dir::{include_dir, Dir};
+
+use crate::error::{Error, Result};
+
+static STARTERS: Dir = include_dir!("$CARGO_MANIFEST_DIR/starters");
+
+/// where the files of a new site come from
+#[derive(Debug, Clone)]
+pub enum Starter {
+    /// one of the starters built into sitdown, by name
+    Builtin(String),
+    /// a directory or an archive of one
+    Path(PathBuf),
+}
+
+impl Starter {
+    /// the names of the built in starters
+    pub fn names() -> Vec<&'static str> {
+        let mut names: Vec<_> = STARTERS
+            .dirs()
+            .filter_map(|dir| dir.path().to_str())
+            .collect();
+        names.sort();
+        names
+    }
+
+    /// the files of the starter by their path within the site
+    fn files(&self) -> Result<Vec<(PathBuf, Vec<u8>)>> {
+        match self {
+            Starter::Builtin(name) => {
+                let dir = STARTERS.get_dir(name).ok_or_else(|| {
+                    Error::NewSiteError(format!(
+                        "unknown starter `{name}`, the starters are {}",
+                        Starter::names()
+                            .iter()
+                            .map(|n| format!("`{n}`"))
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    ))
+                })?;
+                let mut files = Vec::new();
+                builtin_files(dir, &mut files);
+                Ok(files
+                    .into_iter()
+                    .map(|(path, contents)| {
+                        let path = path.strip_prefix(name).unwrap_or(&path).to_path_buf();
+                        (path, contents)
+                    })
+                    .collect())
+            }
+            Starter::Path(path) if path.is_dir() => {
+                let mut files = Vec::new();
+                dir_files(path, path, &mut files)?;
+                Ok(files)
+            }
+            Starter::Path(path) => archive_files(path).map_err(|e| e.in_file(path)),
+        }
+    }
+}
+
+/// create a new site in `dir` from the starter, refusing to write into a directory that has
+/// anything in it unless forced
+pub fn create<T: AsRef<Path>>(dir: T, starter: &Starter, force: bool) -> Result<()> {
+    let dir = dir.as_ref();
+    let in_use = dir.is_file()
+        || fs::read_dir(dir)
+            .map(|mut entries| entries.next().is_some())
+            .unwrap_or(false);
+    if in_use && !force {
+        return Err(Error::NewSiteError(format!(
+            "`{}` already exists and isn't empty, pass `--force` to write over it",
+            dir.display()
+        )));
+    }
+    let files = starter.files()?;
+    if files.is_empty() {
+        return Err(Error::NewSiteError(format!(
+            "the starter {starter:?} doesn't have any files"
+        )));
+    }
+    for (path, contents) in files {
+        let save = dir.join(path);
+        if let Some(parent) = save.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(save, contents)?;
+    }
+    Ok(())
+}
+
+fn builtin_files(dir: &Dir, files: &mut Vec<(PathBuf, Vec<u8>)>) {
+    for file in dir.files() {
+        files.push((file.path().to_path_buf(), file.contents().to_vec()));
+    }
+    for dir in dir.dirs() {
+        builtin_files(dir, files);
+    }
+}
+
+/// the files in a local directory, leaving out the git directory
+fn dir_files(root: &Path, dir: &Path, files: &mut Vec<(PathBuf, Vec<u8>)>) -> Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.file_name().is_some_and(|name| name == ".git") {
+            continue;
+        } else if path.is_dir() {
+            dir_files(root, &path, files)?;
+        } else {
+            let name = path.strip_prefix(root).unwrap_or(&path).to_path_buf();
+            files.push((name, fs::read(&path)?));
+        }
+    }
+    Ok(())
+}
+
+/// the files in a `.tar` or `.tar.gz` archive
+fn archive_files(path: &Path) -> Result<Vec<(PathBuf, Vec<u8>)>> {
+    let name = path.to_string_lossy();
+    let file = File::open(path)?;
+    let reader: Box<dyn Read> = if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+        Box::new(GzDecoder::new(file))
+    } else if name.ends_with(".tar") {
+        Box::new(file)
+    } else {
+        return Err(Error::NewSiteError(format!(
+            "`{name}` isn't a directory or a `.tar` or `.tar.gz` archive"
+        )));
+    };
+    let mut archive = tar::Archive::new(reader);
+    let mut files = Vec::new();
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        let path = entry.path()?.into_owned();
+        // keep everything inside the new site
+        if !path
+            .components()
+            .all(|c| matches!(c, Component::Normal(_) | Component::CurDir))
+        {
+            return Err(Error::NewSiteError(format!(
+                "the archive has a file outside of it, `{}`",
+                path.display()
+            )));
+        }
+        let path: PathBuf = path
+            .components()
+            .filter(|c| matches!(c, Component::Normal(_)))
+            .collect();
+        let mut contents = Vec::new();
+        entry.read_to_end(&mut contents)?;
+        files.push((path, contents));
+    }
+    Ok(without_root(files))
+}
+
+/// take off the directory that all the files are in, if they are all in one
+fn without_root(files: Vec<(PathBuf, Vec<u8>)>) -> Vec<(PathBuf, Vec<u8>)> {
+    let root = files
+        .first()
+        .and_then(|(path, _)| path.components().next())
+        .map(|c| c.as_os_str().to_owned());
+    let Some(root) = root else {
+        return files;
+    };
+    let shared = files.iter().all(|(path, _)| {
+        path.components().count() > 1
+            && path.components().next().map(|c| c.as_os_str()) == Some(root.as_os_str())
+    });
+    if !shared {
+        return files;
+    }
+    files
+        .into_iter()
+        .map(|(path, contents)| {
+            let path = path.strip_prefix(&root).unwrap_or(&path).to_path_buf();
+            (path, contents)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// write a `.tar` with the entries as named, without the checks of `tar::Builder` on the names
+    fn archive(name: &str, entries: &[(&str, &str)]) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("sitdown-{}-{name}.tar", std::process::id()));
+        let mut builder = tar::Builder::new(File::create(&path).unwrap());
+        for (entry, contents) in entries {
+            let mut header = tar::Header::new_old();
+            header.as_old_mut().name[..entry.len()].copy_from_slice(entry.as_bytes());
+            header.set_entry_type(tar::EntryType::Regular);
+            header.set_size(contents.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder.append(&header, contents.as_bytes()).unwrap();
+        }
+        builder.finish().unwrap();
+        path
+    }
+
+    fn empty_files(paths: &[&str]) -> Vec<(PathBuf, Vec<u8>)> {
+        paths
+            .iter()
+            .map(|p| (PathBuf::from(p), Vec::new()))
+            .collect()
+    }
+
+    fn paths(files: &[(PathBuf, Vec<u8>)]) -> Vec<&Path> {
+        files.iter().map(|(path, _)| path.as_path()).collect()
+    }
+
+    #[test]
+    fn entry_outside_the_archive() {
+        let path = archive("parent", &[("site/sitdown.toml", ""), ("site/../../x", "")]);
+        let result = archive_files(&path);
+        fs::remove_file(&path).unwrap();
+        assert!(matches!(result, Err(Error::NewSiteError(_))));
+    }
+
+    #[test]
+    fn absolute_entry() {
+        let path = archive("absolute", &[("/etc/sitdown.toml", "")]);
+        let result = archive_files(&path);
+        fs::remove_file(&path).unwrap();
+        assert!(matches!(result, Err(Error::NewSiteError(_))));
+    }
+
+    #[test]
+    fn files_of_an_archive() {
+        let path = archive(
+            "files",
+            &[("./site/sitdown.toml", "title"), ("site/content/a.md", "")],
+        );
+        let files = archive_files(&path);
+        fs::remove_file(&path).unwrap();
+        let files = files.unwrap();
+        assert_eq!(
+            paths(&files),
+            [Path::new("sitdown.toml"), Path::new("content/a.md")]
+        );
+        assert_eq!(files[0].1, b"title");
+    }
+
+    #[test]
+    fn single_root_is_taken_off() {
+        let files = without_root(empty_files(&["site/sitdown.toml", "site/content/a.md"]));
+        assert_eq!(
+            paths(&files),
+            [Path::new("sitdown.toml"), Path::new("content/a.md")]
+        );
+    }
+
+    #[test]
+    fn mixed_roots_are_kept() {
+        let files = without_root(empty_files(&["sitdown.toml", "content/a.md"]));
+        assert_eq!(
+            paths(&files),
+            [Path::new("sitdown.toml"), Path::new("content/a.md")]
+        );
+        let files = without_root(empty_files(&["one/sitdown.toml", "two/a.md"]));
+        assert_eq!(
+            paths(&files),
+            [Path::new("one/sitdown.toml"), Path::new("two/a.md")]
+        );
+        // a file named like the directory isn't inside of it
+        let files = without_root(empty_files(&["content", "content/a.md"]));
+        assert_eq!(
+            paths(&files),
+            [Path::new("content"), Path::new("content/a.md")]
+        );
+    }
+}
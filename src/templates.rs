@@ -1,26 +1,159 @@
-use std::{fs, io, path::Path};
+use std::{collections::BTreeMap, fs, io, path::Path};
 
-use minijinja::Environment;
+use minijinja::{path_loader, AutoEscape, Environment, ErrorKind, Template};
+
+use crate::{
+    config::ConfigTemplates,
+    error::{self, Result},
+};
+
+/// the subdirectory of the templates holding the shortcode templates
+pub const SHORTCODE_DIR: &str = "shortcodes";
+/// the template used for the rss feeds of directories
+pub const FEED_TEMPLATE: &str = "feed";
+
+/// the listing used for directory index pages when the site doesn't have a template for them
+pub const DEFAULT_SECTION: &str = r#"<!doctype html>
+<html>
+
+<head>
+	<title>{{ title }}</title>
+</head>
+
+<body>
+	<h1>{{ title }}</h1>
+	{{ contents|safe }}
+	<ul>
+		{% for dir in parent.dirs %}
+		<li><a href="/{{ dir.save }}/index.html">{{ dir.title }}</a></li>
+		{% endfor %}
+		{% for page in paginator.pages %}
+		<li><a href="/{{ page.save }}">{{ page.title }}</a></li>
+		{% endfor %}
+	</ul>
+	{% if paginator.prev %}<a href="{{ paginator.prev }}">Previous</a>{% endif %}
+	{% if paginator.next %}<a href="{{ paginator.next }}">Next</a>{% endif %}
+</body>
+
+</html>
+"#;
+
+/// the rss feed used for directories when the site doesn't have a template for it
+pub const DEFAULT_FEED: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<rss version="2.0">
+<channel>
+	<title>{{ site.title|e }}{% if title %} | {{ title|e }}{% endif %}</title>
+	<link>{{ abs_url(save)|e }}</link>
+	<description>{{ site.description|e }}</description>
+	{% for page in parent.pages %}
+	<item>
+		<title>{{ page.title|e }}</title>
+		<link>{{ abs_url(page.save)|e }}</link>
+		<guid>{{ abs_url(page.save)|e }}</guid>
+		{% if page.date is date %}<pubDate>{{ page.date|date("%a, %d %b %Y %H:%M:%S %z") }}</pubDate>{% endif %}
+		<description>{{ page.contents|e }}</description>
+	</item>
+	{% endfor %}
+</channel>
+</rss>
+"#;
 
 /// the templates associated with the site
+///
+/// templates are named by their path within the template directory, like `partials/nav.html`,
+/// and can be referred to without their extension, like `partials/nav`, in which case the first
+/// extension from the config that exists is used
+///
+/// the template directories are layered with the earlier directories overriding the templates
+/// of the same name in the later ones, so a site can override parts of its theme
 // really glad I split this off, would have been to disorganized if I didn't
-pub fn get_env<'a, T: AsRef<Path>>(template_dir: T) -> io::Result<Environment<'a>> {
+pub fn get_env<'a, T: AsRef<Path>>(
+    template_dirs: &[T],
+    config: &ConfigTemplates,
+) -> Result<Environment<'a>> {
     let mut env = Environment::new();
-    for entry in template_dir.as_ref().read_dir()? {
+    // keep the template source around for showing where errors are
+    env.set_debug(true);
+    // the content is already html so leave it to the templates to escape things
+    env.set_auto_escape_callback(|_| AutoEscape::None);
+    let extensions = config.extensions.clone();
+    if config.lazy {
+        let loaders: Vec<_> = template_dirs
+            .iter()
+            .map(|dir| path_loader(dir.as_ref()))
+            .collect();
+        env.set_loader(move |name| {
+            for candidate in candidates(name, &extensions) {
+                for loader in &loaders {
+                    if let Some(source) = loader(&candidate)? {
+                        return Ok(Some(source));
+                    }
+                }
+            }
+            Ok(None)
+        });
+    } else {
+        let mut sources = BTreeMap::new();
+        for dir in template_dirs.iter().rev() {
+            if dir.as_ref().is_dir() {
+                read_templates(dir.as_ref(), dir.as_ref(), &mut sources)?;
+            }
+        }
+        error::collect(
+            sources
+                .iter()
+                .map(|(name, source)| Ok(env.add_template_owned(name.clone(), source.clone())?)),
+        )?;
+        env.set_loader(move |name| {
+            Ok(
+                candidates(name, &extensions)
+                    .find_map(|candidate| sources.get(&candidate).cloned()),
+            )
+        });
+    }
+    Ok(env)
+}
+
+/// the names a template could be stored as, itself first and then with each extension
+fn candidates<'a>(name: &'a str, extensions: &'a [String]) -> impl Iterator<Item = String> + 'a {
+    std::iter::once(name.to_string()).chain(extensions.iter().map(move |e| format!("{name}.{e}")))
+}
+
+/// read all the templates in a directory and its subdirectories, named by their path from the root
+fn read_templates(
+    root: &Path,
+    dir: &Path,
+    sources: &mut BTreeMap<String, String>,
+) -> io::Result<()> {
+    for entry in dir.read_dir()? {
         let entry = entry?;
-        if entry.path().is_file() {
-            let name = entry
-                .path()
-                .file_stem()
-                .unwrap()
-                .to_str()
-                .unwrap()
-                .to_string();
-            env.add_template_owned(name, fs::read_to_string(entry.path())?)
-                .expect("Unable to read template");
+        let path = entry.path();
+        if entry.file_name().to_string_lossy().starts_with('.') {
+            continue;
+        } else if path.is_dir() {
+            read_templates(root, &path, sources)?;
         } else {
-            println!("Skipping `{:?}` while traversing templates", entry.path());
+            let name = path
+                .strip_prefix(root)
+                .unwrap_or(&path)
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            sources.insert(name, fs::read_to_string(&path)?);
         }
     }
-    Ok(env)
+    Ok(())
+}
+
+/// get a template by name, falling back to the given source if the site doesn't have it
+pub fn get_or<'env, 'source>(
+    env: &'env Environment<'source>,
+    name: &str,
+    default: &'source str,
+) -> std::result::Result<Template<'env, 'env>, minijinja::Error> {
+    match env.get_template(name) {
+        Err(err) if err.kind() == ErrorKind::TemplateNotFound => env.template_from_str(default),
+        res => res,
+    }
 }
@@ -3,27 +3,52 @@
 /// out to the files to be served
 ///
 /// transitions are:
-/// file system -> parsed files -> metadata written -> pages generated
-/// Dir<PathBuf, PathBuf> -> Dir<DirInfo, PageInfo> -> Dir<DirPath, PagePath> -> ()
+/// file system -> parsed files -> site model -> pages generated
+/// Dir<PathBuf, PathBuf> -> Dir<DirInfo, PageInfo> -> Arc<Section> -> ()
 use minijinja::{value::Object, Environment};
 use pulldown_cmark::{
-    CowStr::Borrowed,
-    Event::{Start, Text},
-    Options, Parser, Tag, TextMergeStream,
+    Event::{Code, End, InlineHtml, Start, Text},
+    Options, Parser, Tag, TagEnd, TextMergeStream,
 };
+use rayon::prelude::*;
 use std::{
-    collections::HashMap,
+    collections::{hash_map::Entry, BTreeMap, HashMap},
     ffi::OsStr,
     fs::{self, DirEntry},
     path::{Path, PathBuf},
     sync::Arc,
+    time::Instant,
 };
 
-use serde::{Deserialize, Serialize};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use serde_yml::Value;
 
-use crate::error::Result;
-use crate::{config::ConfigDefaults, error::Error, META_FILE};
+use crate::error::{self, Result};
+use crate::{
+    config::ConfigDefaults,
+    error::Error,
+    images::Images,
+    languages::Language,
+    minify,
+    report::RenderTime,
+    shortcodes,
+    templates::{self, FEED_TEMPLATE},
+    META_FILE,
+};
+
+/// the file giving the content and settings for the index page of a directory
+pub const INDEX_FILE: &str = "_index.md";
+/// the file giving the defaults for the pages in a directory and its subdirectories
+pub const DEFAULTS_FILE: &str = "_defaults.yaml";
+/// the page of a page bundle, a directory with the page and the files it uses
+pub const BUNDLE_FILE: &str = "index.md";
+/// the name of the generated index pages
+const INDEX_HTML: &str = "index.html";
+/// the name of the generated rss feeds
+const FEED_FILE: &str = "feed.xml";
+/// the tags around the text the templates leave as it is, like the output of shortcodes
+const RAW_START: &str = "{% raw %}";
+const RAW_END: &str = "{% endraw %}";
 
 // -----
 // The tree datastructure
@@ -31,7 +56,7 @@ use crate::{config::ConfigDefaults, error::Error, META_FILE};
 
 /// the general file tree that should contain the structure of the site
 /// a directory or directory-like thing
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Dir<DirData, PageData> {
     pub data: DirData,
     pub pages: Vec<Page<PageData>>,
@@ -39,14 +64,13 @@ pub struct Dir<DirData, PageData> {
 }
 
 /// a file or file-like thing
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Page<PageData> {
     pub data: PageData,
 }
 
 /// the initial parsing of the file system should result in a file system with
 /// path to the actual file system
-
 impl<D, P> Dir<D, P> {
     pub fn pages<'a>(&'a self) -> PagesIter<'a, P> {
         PagesIter {
@@ -116,13 +140,14 @@ pub enum File<'a, D, P> {
 // The file sytem stored in the tree
 // -----
 
-/// read the contents of a directory
+/// read the contents of a directory, sorted by path so the site is the same on every filesystem
 pub fn load_contents<T: AsRef<Path>>(path: T) -> std::io::Result<Dir<PathBuf, PathBuf>> {
     let path = path.as_ref();
     let mut pages = Vec::new();
     let mut dirs = Vec::new();
-    for entry in path.read_dir()? {
-        let entry = entry?;
+    let mut entries = path.read_dir()?.collect::<std::io::Result<Vec<_>>>()?;
+    entries.sort_by_key(|entry| entry.path());
+    for entry in entries {
         if entry.path().is_dir() {
             dirs.push(load_contents(entry.path())?);
         } else {
@@ -145,31 +170,210 @@ impl Dir<PathBuf, PathBuf> {
         &self,
         config: &ConfigDefaults,
         options: &Options,
+        templates: &Environment,
+        images: &Images,
+        language: &Language,
+    ) -> Result<Dir<DirInfo, PageInfo>> {
+        self.annotate_with(
+            config,
+            options,
+            templates,
+            images,
+            language,
+            &HashMap::new(),
+        )
+    }
+
+    /// annotate with the defaults cascading down from the parent directories
+    fn annotate_with(
+        &self,
+        config: &ConfigDefaults,
+        options: &Options,
+        templates: &Environment,
+        images: &Images,
+        language: &Language,
+        inherited: &HashMap<String, Value>,
     ) -> Result<Dir<DirInfo, PageInfo>> {
-        let pages: Result<Vec<_>> = self.pages().map(|p| p.annotate(config, options)).collect();
-        let dirs: Result<Vec<_>> = self.dirs().map(|d| d.annotate(config, options)).collect();
+        let index = self.pages().find(|p| is_index(&language.strip(&p.data)));
+        let mut index_content = index
+            .map(|index| {
+                PageContent::read(&index.data, options, templates, images)
+                    .map_err(|e| e.in_file(&index.data))
+            })
+            .transpose();
+        let file_defaults = self
+            .pages()
+            .find(|p| is_defaults(&p.data))
+            .map(|file| read_defaults(&file.data).map_err(|e| e.in_file(&file.data)))
+            .transpose();
+
+        // the defaults closest to the page take precedence
+        let mut defaults = inherited.clone();
+        if let Ok(Some(values)) = &file_defaults {
+            defaults.extend(values.clone());
+        }
+        if let Ok(Some(content)) = &mut index_content {
+            if let Some(cascade) = take::<HashMap<String, Value>>(&mut content.meta, "cascade") {
+                defaults.extend(cascade);
+            }
+        }
+
+        let (files, assets): (Vec<_>, Vec<_>) = self
+            .pages()
+            .filter(|p| !is_index(&language.strip(&p.data)) && !is_defaults(&p.data))
+            .partition(|p| ContentType::of(&p.data) != ContentType::Asset);
+        let (bundles, dirs): (Vec<_>, Vec<_>) = self.dirs().partition(|d| d.is_bundle(language));
+        // the pages are parsed in parallel, collecting into a vec keeps them in order
+        let pages: Vec<_> = files
+            .into_par_iter()
+            .map(|p| p.annotate(config, options, templates, images, language, &defaults))
+            .chain(bundles.into_par_iter().map(|d| {
+                d.annotate_bundle(config, options, templates, images, language, &defaults)
+            }))
+            .collect();
+        let dirs: Vec<_> = dirs
+            .into_par_iter()
+            .map(|d| d.annotate_with(config, options, templates, images, language, &defaults))
+            .collect();
+        let (pages, dirs) = (error::collect(pages), error::collect(dirs));
+        let (index_content, mut pages, dirs) = match (index_content, file_defaults, pages, dirs) {
+            (Ok(index_content), Ok(_), Ok(pages), Ok(dirs)) => (index_content, pages, dirs),
+            (index_content, file_defaults, pages, dirs) => {
+                return Err(Error::from_errors(
+                    [
+                        index_content.err(),
+                        file_defaults.err(),
+                        pages.err(),
+                        dirs.err(),
+                    ]
+                    .into_iter()
+                    .flatten(),
+                ))
+            }
+        };
+
+        let index_page = pages.iter().position(|p| {
+            !p.data.bundle && p.data.save.file_name() == Some(OsStr::new(INDEX_HTML))
+        });
+        if let (Some(index), Some(position)) = (index, index_page) {
+            // both would be written to `index.html`, so only the directory index is kept
+            let page = pages.remove(position);
+            log::warn!(
+                "`{:?}` is replaced by the directory index `{:?}`",
+                page.data.save,
+                index.data
+            );
+        }
+        let generate = index.is_some() || index_page.is_none();
         Ok(Dir {
-            data: DirInfo::new(&self.data)?,
-            pages: pages?,
-            dirs: dirs?,
+            data: DirInfo::new(
+                &self.data,
+                index.map(|index| index.data.as_path()),
+                index_content.unwrap_or_default(),
+                generate,
+                assets.into_iter().map(|p| p.data.clone()).collect(),
+                config,
+                language,
+            )?,
+            pages,
+            dirs,
         })
     }
 
-    pub fn copy_to<T: AsRef<Path>>(&self, to: T) -> std::io::Result<()> {
-        fs::create_dir_all(to.as_ref().join(&self.data))?;
-        for dir in self.dirs() {
-            dir.copy_to(to.as_ref())?;
+    /// whether the directory is a page bundle, an `index.md` with the files it uses and no other
+    /// pages or subdirectories
+    fn is_bundle(&self, language: &Language) -> bool {
+        self.dirs.is_empty()
+            && self
+                .pages()
+                .any(|p| is_bundle_index(&language.strip(&p.data)))
+            && self.pages().all(|p| {
+                is_bundle_index(&language.strip(&p.data))
+                    || is_defaults(&p.data)
+                    || ContentType::of(&p.data) == ContentType::Asset
+            })
+    }
+
+    /// annotate a page bundle as a single page with the other files as its resources
+    fn annotate_bundle(
+        &self,
+        config: &ConfigDefaults,
+        options: &Options,
+        templates: &Environment,
+        images: &Images,
+        language: &Language,
+        defaults: &HashMap<String, Value>,
+    ) -> Result<Page<PageInfo>> {
+        let index = self
+            .pages()
+            .find(|p| is_bundle_index(&language.strip(&p.data)))
+            .ok_or_else(|| Error::DirError(self.data.clone()))?;
+        let mut page = index.annotate(config, options, templates, images, language, defaults)?;
+        let resources = self
+            .pages()
+            .filter(|p| !is_bundle_index(&language.strip(&p.data)) && !is_defaults(&p.data))
+            .map(|p| Resource::new(&p.data, &page.data.save).map_err(|e| e.in_file(&p.data)));
+        page.data.resources = error::collect(resources)?;
+        page.data.bundle = true;
+        Ok(page)
+    }
+
+    /// the files of the directory for a language, when the languages share the content the
+    /// directories without any pages in the language are left out
+    pub fn in_language(&self, language: &Language) -> Self {
+        let pages = self
+            .pages()
+            .filter(|p| {
+                ContentType::of(&p.data) == ContentType::Asset || language.includes(&p.data)
+            })
+            .map(|p| Page {
+                data: p.data.clone(),
+            })
+            .collect();
+        let dirs = self
+            .dirs()
+            .map(|d| d.in_language(language))
+            .filter(|d| !language.shares_content() || d.has_pages())
+            .collect();
+        Dir {
+            data: self.data.clone(),
+            pages,
+            dirs,
         }
-        for page in self.pages() {
-            fs::copy(&page.data, to.as_ref().join(&page.data))?;
+    }
+
+    /// whether there are any pages in the directory or its subdirectories
+    fn has_pages(&self) -> bool {
+        self.pages()
+            .any(|p| ContentType::of(&p.data) != ContentType::Asset)
+            || self.dirs().any(Dir::has_pages)
+    }
+
+    /// all the files in the directory and its subdirectories
+    pub fn files(&self) -> Vec<&PathBuf> {
+        let mut files: Vec<_> = self.pages().map(|p| &p.data).collect();
+        for dir in self.dirs() {
+            files.extend(dir.files());
         }
-        Ok(())
+        files
     }
 }
 
 impl Page<PathBuf> {
-    pub fn annotate(&self, config: &ConfigDefaults, options: &Options) -> Result<Page<PageInfo>> {
-        PageInfo::new(&self.data, config, options).map(|x| Page { data: x })
+    pub fn annotate(
+        &self,
+        config: &ConfigDefaults,
+        options: &Options,
+        templates: &Environment,
+        images: &Images,
+        language: &Language,
+        defaults: &HashMap<String, Value>,
+    ) -> Result<Page<PageInfo>> {
+        PageInfo::new(
+            &self.data, config, options, templates, images, language, defaults,
+        )
+        .map(|x| Page { data: x })
+        .map_err(|e| e.in_file(&self.data))
     }
 }
 
@@ -177,72 +381,351 @@ impl Page<PathBuf> {
 // The tree after parsing the files in the file system
 // -----
 
-/// write the metadata out to a working directory so that it can be read as needed when
-/// generating the actual pages, then keep track of the paths to the metadata or directory
-/// paths
+/// turn the parsed tree into the site model that the pages are rendered from, the metadata can
+/// also be written out to a working directory to look at when debugging the site
 impl Dir<DirInfo, PageInfo> {
-    pub fn write_metadata<T: AsRef<Path>>(&self, work_dir: T) -> Result<Dir<DirPath, PagePath>> {
+    pub fn into_section(self) -> Arc<Section> {
+        let mut pages: Vec<PageInfo> = self.pages.into_iter().map(|p| p.data).collect();
+        self.data.sort(&mut pages);
+        let mut dirs: Vec<_> = self.dirs.into_iter().map(Dir::into_section).collect();
+        dirs.sort_by(|a, b| a.info.save.cmp(&b.info.save));
+        Arc::new(Section {
+            info_value: minijinja::Value::from_serialize(&self.data),
+            pages_value: minijinja::Value::from_serialize(&pages),
+            info: self.data,
+            pages,
+            dirs,
+        })
+    }
+
+    /// turn the tree into the site model, after rendering the contents of the files with
+    /// `render_content` through the templates with a first model of the site to look at
+    pub fn render_contents(
+        mut self,
+        templates: &Environment,
+        options: &Options,
+        images: &Images,
+    ) -> Result<Arc<Section>> {
+        if !self.has_unrendered() {
+            return Ok(self.into_section());
+        }
+        let root = self.clone().into_section();
+        self.render_contents_with(&root, &root, templates, options, images)?;
+        Ok(self.into_section())
+    }
+
+    fn has_unrendered(&self) -> bool {
+        self.data.meta.unrendered.is_some()
+            || self.pages.iter().any(|p| p.data.meta.unrendered.is_some())
+            || self.dirs.iter().any(Dir::has_unrendered)
+    }
+
+    fn render_contents_with(
+        &mut self,
+        section: &Arc<Section>,
+        root: &Arc<Section>,
+        templates: &Environment,
+        options: &Options,
+        images: &Images,
+    ) -> Result<()> {
+        let meta = MetaObject {
+            root: root.clone(),
+            parent: section.clone(),
+            page: section.info_value.clone(),
+            paginator: None,
+        };
+        let index = self
+            .data
+            .meta
+            .render(meta, templates, options, images)
+            .map_err(|e| self.data.in_file(e));
+        let pages: Vec<_> = self
+            .pages
+            .par_iter_mut()
+            .map(|page| {
+                let meta = MetaObject {
+                    root: root.clone(),
+                    parent: section.clone(),
+                    page: minijinja::Value::from_serialize(&page.data),
+                    paginator: None,
+                };
+                let file = page.data.file.clone();
+                page.data
+                    .meta
+                    .render(meta, templates, options, images)
+                    .map_err(|e| e.in_file(file))
+            })
+            .collect();
+        let dirs: Vec<_> = self
+            .dirs
+            .iter_mut()
+            .map(
+                |dir| match section.dirs.iter().find(|s| s.info.save == dir.data.save) {
+                    Some(inner) => {
+                        dir.render_contents_with(inner, root, templates, options, images)
+                    }
+                    None => Ok(()),
+                },
+            )
+            .collect();
+        error::collect([index].into_iter().chain(pages).chain(dirs))?;
+        Ok(())
+    }
+
+    /// take out the pages marked with `draft: true`, giving how many there were
+    pub fn remove_drafts(&mut self) -> usize {
+        let before = self.pages.len();
+        self.pages.retain(|page| !page.data.is_draft());
+        let removed = before - self.pages.len();
+        removed + self.dirs.iter_mut().map(Dir::remove_drafts).sum::<usize>()
+    }
+
+    /// the pages and directories of the tree by their location in the content
+    fn find_translations(&self, found: &mut HashMap<PathBuf, Vec<Translation>>) {
+        let info = &self.data;
+        found
+            .entry(info.source.clone())
+            .or_default()
+            .push(Translation::new(&info.lang, &info.title, &info.save));
+        for page in self.pages() {
+            let page = &page.data;
+            found
+                .entry(page.source.clone())
+                .or_default()
+                .push(Translation::new(&page.lang, &page.title, &page.save));
+        }
+        for dir in self.dirs() {
+            dir.find_translations(found);
+        }
+    }
+
+    fn set_translations(&mut self, found: &HashMap<PathBuf, Vec<Translation>>) {
+        self.data.translations = translations_of(found, &self.data.source, &self.data.lang);
+        for page in &mut self.pages {
+            page.data.translations = translations_of(found, &page.data.source, &page.data.lang);
+        }
+        for dir in &mut self.dirs {
+            dir.set_translations(found);
+        }
+    }
+
+    pub fn write_metadata<T: AsRef<Path>>(&self, work_dir: T) -> Result<()> {
         let dir_path = work_dir.as_ref().join(&self.data.save);
         fs::create_dir_all(&dir_path)?;
         let contents = serde_yml::to_string(&self.data)?;
         fs::write(dir_path.join(META_FILE), contents)?;
-        let data = DirPath {
-            path: dir_path,
-            orig: self.data.save.clone(),
-        };
+        for dir in self.dirs() {
+            dir.write_metadata(work_dir.as_ref())?;
+        }
+        for page in self.pages() {
+            page.write_metadata(work_dir.as_ref())?;
+        }
+        Ok(())
+    }
+}
 
-        let dirs: Result<Vec<_>> = self
-            .dirs()
-            .map(|d| d.write_metadata(work_dir.as_ref()))
-            .collect();
-        let pages: Result<Vec<_>> = self
-            .pages()
-            .map(|p| p.write_metadata(work_dir.as_ref()))
-            .collect();
+/// link the pages and directories of each language to their versions in the other languages,
+/// matched by their location in the content
+pub fn link_translations(trees: &mut [Dir<DirInfo, PageInfo>]) {
+    let mut found = HashMap::new();
+    for tree in trees.iter() {
+        tree.find_translations(&mut found);
+    }
+    for tree in trees.iter_mut() {
+        tree.set_translations(&found);
+    }
+}
 
-        Ok(Dir {
-            data,
-            dirs: dirs?,
-            pages: pages?,
-        })
+/// the versions of the file at `source` in the languages other than `lang`
+fn translations_of(
+    found: &HashMap<PathBuf, Vec<Translation>>,
+    source: &Path,
+    lang: &str,
+) -> Vec<Translation> {
+    found
+        .get(source)
+        .map(|all| all.iter().filter(|t| t.lang != lang).cloned().collect())
+        .unwrap_or_default()
+}
+
+/// a page or directory in another language
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Translation {
+    /// the code of the language
+    lang: String,
+    title: String,
+    /// location of the file in the site
+    save: PathBuf,
+}
+
+impl Translation {
+    fn new(lang: &str, title: &str, save: &Path) -> Self {
+        Self {
+            lang: lang.into(),
+            title: title.into(),
+            save: save.into(),
+        }
     }
 }
 
 impl Page<PageInfo> {
-    fn write_metadata<T: AsRef<Path>>(&self, work_dir: T) -> Result<Page<PagePath>> {
-        let mut page_path = work_dir.as_ref().join(&self.data.save);
-        page_path.set_extension("yaml");
+    fn write_metadata<T: AsRef<Path>>(&self, work_dir: T) -> Result<()> {
+        // bundles are kept with the other pages of the directory they are in
+        let save = match self.data.save.parent() {
+            Some(parent) if self.data.bundle => parent,
+            _ => &self.data.save,
+        };
+        let page_path = work_dir.as_ref().join(save).with_extension("yaml");
         let content = serde_yml::to_string(&self.data)?;
         fs::write(&page_path, content)?;
-        let data = PagePath {
-            path: page_path,
-            orig: self.data.save.clone(),
-            template: self.data.template.clone(),
-        };
-        Ok(Page { data })
+        Ok(())
     }
 }
 
 /// data for creating the output directory
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DirInfo {
     /// location to save the directory to
     save: PathBuf,
     /// name of the directory
     title: String,
+    /// the name of the template to use for the index page
+    template: String,
+    /// the page value to sort the pages of the directory by, defaults to the save location
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    sort_by: Option<String>,
+    /// sort the pages in descending order instead
+    #[serde(default)]
+    sort_reverse: bool,
+    /// the number of pages to list on each index page, everything goes on one page if not given
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    paginate: Option<usize>,
+    /// whether to generate an rss feed of the pages in the directory
+    #[serde(default)]
+    feed: bool,
+    /// whether to generate an index page for the directory
+    #[serde(default)]
+    index: bool,
+    /// the files in the directory that aren't pages, copied next to the pages as they are
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    assets: Vec<PathBuf>,
+    /// the language of the directory
+    lang: String,
+    /// the directory in the other languages
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    translations: Vec<Translation>,
+    /// location of the directory within the content, the same in every language
+    #[serde(skip)]
+    source: PathBuf,
+    /// the `_index.md` the directory was read from, if it has one
+    #[serde(skip)]
+    file: Option<PathBuf>,
+    /// the data to be provided to the template
+    #[serde(flatten)]
+    meta: Metadata,
 }
 
 impl DirInfo {
-    fn new<T: AsRef<Path>>(path: T) -> Result<Self> {
-        PathInfo::dir(path).map(|x| DirInfo {
-            title: x.title,
-            save: x.save,
+    /// the info for a directory, using the contents of the `_index.md` if there is one
+    fn new<T: AsRef<Path>>(
+        path: T,
+        file: Option<&Path>,
+        mut page_content: PageContent,
+        index: bool,
+        assets: Vec<PathBuf>,
+        config: &ConfigDefaults,
+        language: &Language,
+    ) -> Result<Self> {
+        let info = PathInfo::dir(path, language)?;
+        let meta = &mut page_content.meta;
+        // the language comes from where the directory is
+        meta.remove("lang");
+        let title = take(meta, "title").unwrap_or(info.title);
+        let template = take(meta, "template").unwrap_or_else(|| config.section.clone());
+        let sort_by = take(meta, "sort_by");
+        let sort_reverse = take(meta, "sort_reverse").unwrap_or_default();
+        let paginate = take(meta, "paginate");
+        let feed = take(meta, "feed").unwrap_or_default();
+        let meta = Metadata {
+            contents: page_content.contents,
+            unrendered: page_content.unrendered,
+            meta: page_content.meta,
+        };
+
+        Ok(DirInfo {
+            save: info.save,
+            title,
+            template,
+            sort_by,
+            sort_reverse,
+            paginate,
+            feed,
+            index,
+            assets,
+            lang: language.code.clone(),
+            translations: Vec::new(),
+            source: info.source,
+            file: file.map(Path::to_path_buf),
+            meta,
         })
     }
+
+    /// point an error at the `_index.md` of the directory, if it has one
+    fn in_file(&self, err: Error) -> Error {
+        match &self.file {
+            Some(file) => err.in_file(file),
+            None => err,
+        }
+    }
+
+    /// sort the pages of the directory by the requested value
+    fn sort(&self, pages: &mut [PageInfo]) {
+        match &self.sort_by {
+            Some(key) => pages.sort_by_cached_key(|p| {
+                minijinja::Value::from_serialize(p)
+                    .get_attr(key)
+                    .unwrap_or_default()
+            }),
+            None => pages.sort_by(|a, b| a.save.cmp(&b.save)),
+        }
+        if self.sort_reverse {
+            pages.reverse();
+        }
+    }
+}
+
+/// read the defaults for the pages in a directory from its `_defaults.yaml`
+fn read_defaults(path: &Path) -> Result<HashMap<String, Value>> {
+    let contents = fs::read_to_string(path)?;
+    let values: Option<HashMap<String, Value>> = serde_yml::from_str(&contents)?;
+    Ok(values.unwrap_or_default())
+}
+
+/// fill in the metadata of a page that it doesn't give itself, the defaults of its directories
+/// take precedence over the rules of the config
+fn fill_defaults(
+    meta: &mut HashMap<String, Value>,
+    defaults: &HashMap<String, Value>,
+    rules: BTreeMap<String, figment::value::Value>,
+) -> Result<()> {
+    for (key, value) in defaults {
+        meta.entry(key.clone()).or_insert_with(|| value.clone());
+    }
+    for (key, value) in rules {
+        if let Entry::Vacant(entry) = meta.entry(key) {
+            entry.insert(serde_yml::to_value(value)?);
+        }
+    }
+    Ok(())
+}
+
+/// take a value out of the metadata, ignoring it if it isn't the expected type
+fn take<T: DeserializeOwned>(meta: &mut HashMap<String, Value>, key: &str) -> Option<T> {
+    meta.remove(key).and_then(|v| serde_yml::from_value(v).ok())
 }
 
 /// data for creating the output file
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PageInfo {
     /// the name of the template to use
     template: String,
@@ -250,16 +733,48 @@ pub struct PageInfo {
     save: PathBuf,
     /// the title for the file
     title: String,
+    /// the files of a page bundle, copied next to the page
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    resources: Vec<Resource>,
+    /// whether the page is the page of a bundle
+    #[serde(skip)]
+    bundle: bool,
+    /// the language of the page
+    lang: String,
+    /// the page in the other languages
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    translations: Vec<Translation>,
+    /// location of the file within the content, the same in every language
+    #[serde(skip)]
+    source: PathBuf,
+    /// the file the page was read from, for pointing errors at it
+    #[serde(skip)]
+    file: PathBuf,
     /// the data to be provided to the template
     #[serde(flatten)]
     meta: Metadata,
 }
 
 impl PageInfo {
-    fn new<T: AsRef<Path>>(path: T, config: &ConfigDefaults, options: &Options) -> Result<Self> {
+    fn new<T: AsRef<Path>>(
+        path: T,
+        config: &ConfigDefaults,
+        options: &Options,
+        templates: &Environment,
+        images: &Images,
+        language: &Language,
+        defaults: &HashMap<String, Value>,
+    ) -> Result<Self> {
         let path = path.as_ref();
-        let info = PathInfo::page(&path)?;
-        let mut page_content = PageContent::read(&path, options)?;
+        let info = PathInfo::page(path, language)?;
+        let mut page_content = PageContent::read(path, options, templates, images)?;
+        // the language comes from the name of the page
+        page_content.meta.remove("lang");
+        fill_defaults(
+            &mut page_content.meta,
+            defaults,
+            config.matching(&info.source),
+        )?;
         let title = if let Some(t) = page_content.meta.remove("title") {
             t.as_str().unwrap_or(&info.title).into()
         } else {
@@ -271,8 +786,10 @@ impl PageInfo {
             config.page.clone()
         };
         let save = info.save;
+        let source = info.source;
         let meta = Metadata {
             contents: page_content.contents,
+            unrendered: page_content.unrendered,
             meta: page_content.meta,
         };
 
@@ -280,40 +797,323 @@ impl PageInfo {
             template,
             save,
             title,
+            resources: Vec::new(),
+            bundle: false,
+            lang: language.code.clone(),
+            translations: Vec::new(),
+            source,
+            file: path.to_path_buf(),
             meta,
         })
     }
+
+    /// the location of the page in the site
+    pub fn save(&self) -> &Path {
+        &self.save
+    }
+
+    /// the location of the page within the content, the same in every language
+    pub fn source(&self) -> &Path {
+        &self.source
+    }
+
+    pub fn title(&self) -> &str {
+        &self.title
+    }
+
+    pub fn lang(&self) -> &str {
+        &self.lang
+    }
+
+    /// a value from the metadata of the page
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        self.meta.meta.get(key)
+    }
+
+    /// whether the page is marked as a draft, to be left out of the site
+    pub fn is_draft(&self) -> bool {
+        self.get("draft").and_then(Value::as_bool).unwrap_or(false)
+    }
+
+    /// the contents of the page as html, before it is rendered with its template
+    pub fn html(&self) -> &str {
+        &self.meta.contents
+    }
+}
+
+/// a file of a page bundle
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Resource {
+    /// the name of the file
+    name: String,
+    /// the url of the file in the site
+    path: String,
+    /// the mime type of the file
+    mime: String,
+    /// the width of the file if it is an image
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    width: Option<usize>,
+    /// the height of the file if it is an image
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    height: Option<usize>,
+    /// the location of the file to copy from
+    #[serde(skip)]
+    source: PathBuf,
+}
+
+impl Resource {
+    /// the resource at `path`, for the page saved to `save`
+    fn new(path: &Path, save: &Path) -> Result<Self> {
+        let name = path
+            .file_name()
+            .and_then(OsStr::to_str)
+            .ok_or_else(|| Error::PageError(path.into()))?
+            .to_string();
+        let url = save.parent().unwrap_or(Path::new("")).join(&name);
+        let size = imagesize::size(path).ok();
+        Ok(Self {
+            path: format!("/{}", url.to_string_lossy()),
+            mime: mime_guess::from_path(path)
+                .first_or_octet_stream()
+                .to_string(),
+            width: size.map(|s| s.width),
+            height: size.map(|s| s.height),
+            source: path.into(),
+            name,
+        })
+    }
 }
 
+#[derive(Default)]
 struct PageContent {
     contents: String,
+    unrendered: Option<Unrendered>,
     meta: HashMap<String, Value>,
 }
 
+/// the contents of a file with `render_content`, rendered through the templates once there is a
+/// model of the site to render them with
+#[derive(Debug, Clone)]
+struct Unrendered {
+    text: String,
+    content_type: ContentType,
+    /// the directory of the file, for finding the images in the markdown
+    dir: PathBuf,
+}
+
 impl PageContent {
-    fn read<T: AsRef<Path>>(path: T, options: &Options) -> Result<PageContent> {
+    fn read<T: AsRef<Path>>(
+        path: T,
+        options: &Options,
+        templates: &Environment,
+        images: &Images,
+    ) -> Result<PageContent> {
+        let path = path.as_ref();
+        let content_type = ContentType::of(path);
         let text = fs::read_to_string(path)?;
-        let parser = Parser::new_ext(&text, *options);
-        let mut iterator = TextMergeStream::new(parser).peekable();
-
-        let meta = if let Some(Start(Tag::MetadataBlock(_))) = iterator.peek() {
-            iterator.next();
-            let info = if let Some(Text(Borrowed(s))) = iterator.peek() {
-                let res = serde_yml::from_str(s)?;
-                iterator.next(); // skip the parsed data
-                iterator.next(); // skip the End token
-                res
-            } else {
-                HashMap::new()
+        // the shortcodes of pages rendered through the templates are kept out of the rendering
+        let (meta, _) = split_metadata(&text, options)?;
+        let text = shortcodes::expand(&text, templates, options, render_content(&meta))?;
+        let (meta, body) = split_metadata(&text, options)?;
+        let page_dir = path.parent().unwrap_or(Path::new(""));
+        if render_content(&meta) {
+            // until they are rendered the template tags are left out of the contents, so the
+            // other pages don't see them
+            let text = without_template_tags(body);
+            let contents = match content_type {
+                ContentType::Markdown => markdown(&text, options),
+                _ => text,
             };
-            info
-        } else {
-            HashMap::new()
+            let unrendered = Unrendered {
+                text: body.into(),
+                content_type,
+                dir: page_dir.into(),
+            };
+            return Ok(Self {
+                contents,
+                unrendered: Some(unrendered),
+                meta,
+            });
+        }
+        let contents = match content_type {
+            ContentType::Html => body.into(),
+            _ => markdown_with_images(body, options, images, page_dir)?,
+        };
+
+        Ok(Self {
+            contents,
+            unrendered: None,
+            meta,
+        })
+    }
+}
+
+/// split the yaml metadata block off the start of the markdown
+fn split_metadata<'a>(
+    text: &'a str,
+    options: &Options,
+) -> Result<(HashMap<String, Value>, &'a str)> {
+    let mut iterator = Parser::new_ext(text, *options).into_offset_iter();
+    if let Some((Start(Tag::MetadataBlock(_)), _)) = iterator.next() {
+        let mut info = String::new();
+        let mut start = 0;
+        for (event, range) in iterator {
+            match event {
+                Text(s) => {
+                    if info.is_empty() {
+                        start = range.start;
+                    }
+                    info.push_str(&s);
+                }
+                End(TagEnd::MetadataBlock(_)) => {
+                    let meta = if info.trim().is_empty() {
+                        HashMap::new()
+                    } else {
+                        serde_yml::from_str(&info).map_err(|e| {
+                            let index = e.location().map(|l| l.index()).unwrap_or_default();
+                            Error::from(e).at_offset(start + index)
+                        })?
+                    };
+                    return Ok((meta, &text[range.end..]));
+                }
+                _ => {}
+            }
+        }
+    }
+    Ok((HashMap::new(), text))
+}
+
+/// convert markdown to html
+fn markdown(text: &str, options: &Options) -> String {
+    let parser = TextMergeStream::new(Parser::new_ext(text, *options));
+    let mut contents = String::new();
+    pulldown_cmark::html::push_html(&mut contents, parser);
+    contents
+}
+
+/// how a file in the content directory is handled
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum ContentType {
+    /// converted to html then rendered with the page template
+    #[default]
+    Markdown,
+    /// rendered with the page template as it is
+    Html,
+    /// copied next to the pages without any changes
+    Asset,
+}
+
+impl ContentType {
+    /// the type of a file from its extension
+    pub fn of(path: &Path) -> Self {
+        match path.extension().and_then(OsStr::to_str) {
+            Some("md" | "markdown") => ContentType::Markdown,
+            Some("html" | "htm") => ContentType::Html,
+            _ => ContentType::Asset,
+        }
+    }
+}
+
+/// convert markdown to html, giving the local images their sizes and a `srcset` of resized
+/// versions
+fn markdown_with_images(
+    text: &str,
+    options: &Options,
+    images: &Images,
+    page_dir: &Path,
+) -> Result<String> {
+    let mut events = Vec::new();
+    let mut parser = TextMergeStream::new(Parser::new_ext(text, *options));
+    while let Some(event) = parser.next() {
+        let Start(Tag::Image {
+            dest_url, title, ..
+        }) = &event
+        else {
+            events.push(event);
+            continue;
+        };
+        let Some(source) = images
+            .resolve(dest_url, page_dir)
+            .filter(|source| Images::can_resize(source))
+        else {
+            events.push(event);
+            continue;
         };
-        let mut contents = String::new();
-        pulldown_cmark::html::push_html(&mut contents, iterator);
+        // the alt text is the text up to the end of the image
+        let mut alt = String::new();
+        let mut inner_events = Vec::new();
+        for inner in parser.by_ref() {
+            match &inner {
+                End(TagEnd::Image) => {
+                    inner_events.push(inner);
+                    break;
+                }
+                Text(text) | Code(text) => alt.push_str(text),
+                _ => {}
+            }
+            inner_events.push(inner);
+        }
+        // images that can't be read are left as they are in the markdown
+        match images.img_tag(&source, dest_url, &alt, title) {
+            Ok(tag) => events.push(InlineHtml(tag.into())),
+            Err(err) => {
+                log::warn!("Leaving the image `{dest_url}` as it is: {err}");
+                events.push(event);
+                events.extend(inner_events);
+            }
+        }
+    }
+    let mut contents = String::new();
+    pulldown_cmark::html::push_html(&mut contents, events.into_iter());
+    Ok(contents)
+}
 
-        Ok(Self { contents, meta })
+/// whether the page asked for its markdown to be rendered through the templates
+fn render_content(meta: &HashMap<String, Value>) -> bool {
+    meta.get("render_content")
+        .and_then(Value::as_bool)
+        .unwrap_or(false)
+}
+
+/// the text with the `{{ }}`, `{% %}`, and `{# #}` tags of the templates removed
+fn without_template_tags(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('{') {
+        let close = match rest[start + 1..].chars().next() {
+            Some('{') => "}}",
+            Some('%') => "%}",
+            Some('#') => "#}",
+            _ => {
+                out.push_str(&rest[..=start]);
+                rest = &rest[start + 1..];
+                continue;
+            }
+        };
+        out.push_str(&rest[..start]);
+        // raw blocks, like the output of shortcodes, are kept as they are
+        if let Some(raw) = rest[start..].strip_prefix(RAW_START) {
+            let end = raw.find(RAW_END).unwrap_or(raw.len());
+            out.push_str(&raw[..end]);
+            rest = raw.get(end + RAW_END.len()..).unwrap_or_default();
+            continue;
+        }
+        match rest[start + 2..].find(close) {
+            Some(end) => rest = &rest[start + 2 + end + close.len()..],
+            None => rest = "",
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// minify the rendered page unless it opted out with `minify: false`
+fn minified(content: String, minify: bool, meta: &HashMap<String, Value>) -> String {
+    let wanted = meta.get("minify").and_then(Value::as_bool).unwrap_or(true);
+    if minify && wanted {
+        minify::html(&content)
+    } else {
+        content
     }
 }
 
@@ -323,42 +1123,55 @@ struct PathInfo {
     title: String,
     /// save location of the file
     save: PathBuf,
+    /// location of the file within the content directory
+    source: PathBuf,
 }
 
 /// based on how the path is retrieved this should really be infallible
 impl PathInfo {
-    /// the generated page info from it's path
-    fn page<T: AsRef<Path>>(path: T) -> Result<Self> {
-        let path = path.as_ref();
-        let title = path
-            .file_stem()
+    /// the generated page info from it's path, pages of languages other than the default are
+    /// saved under the code of their language
+    fn page<T: AsRef<Path>>(path: T, language: &Language) -> Result<Self> {
+        let path = &language.strip(path.as_ref());
+        // the page of a bundle is named after its directory
+        let name = match path.parent() {
+            Some(parent) if is_bundle_index(path) => parent.file_name(),
+            _ => path.file_stem(),
+        };
+        let title = name
             .ok_or_else(|| Error::PageError(path.into()))?
             .to_str()
             .ok_or_else(|| Error::PageError(path.into()))?
-            .replace("_", " ")
-            .into();
-        let mut save = PathBuf::new();
+            .replace("_", " ");
+        let mut source = PathBuf::new();
         for component in path.components().skip(1) {
-            save.push(component);
+            source.push(component);
         }
-        save.set_extension("html");
-        Ok(Self { title, save })
+        let save = language.prefix.join(&source).with_extension("html");
+        Ok(Self {
+            title,
+            save,
+            source,
+        })
     }
     /// the generated dir info from it's path
-    fn dir<T: AsRef<Path>>(path: T) -> Result<Self> {
+    fn dir<T: AsRef<Path>>(path: T, language: &Language) -> Result<Self> {
         let path = path.as_ref();
         let title = path
             .file_name()
             .ok_or_else(|| Error::DirError(path.into()))?
             .to_str()
             .ok_or_else(|| Error::DirError(path.into()))?
-            .replace("_", " ")
-            .into();
-        let mut save = PathBuf::new();
+            .replace("_", " ");
+        let mut source = PathBuf::new();
         for component in path.components().skip(1) {
-            save.push(component);
+            source.push(component);
         }
-        Ok(Self { title, save })
+        Ok(Self {
+            title,
+            save: language.prefix.join(&source),
+            source,
+        })
     }
 }
 
@@ -367,129 +1180,335 @@ impl PathInfo {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 struct Metadata {
     contents: String,
+    #[serde(skip)]
+    unrendered: Option<Unrendered>,
     #[serde(flatten)]
     meta: HashMap<String, Value>,
 }
 
+impl Metadata {
+    /// render the contents through the templates if the file asked for it
+    fn render(
+        &mut self,
+        meta: MetaObject,
+        templates: &Environment,
+        options: &Options,
+        images: &Images,
+    ) -> Result<()> {
+        let Some(unrendered) = self.unrendered.take() else {
+            return Ok(());
+        };
+        let body = templates.render_str(&unrendered.text, minijinja::Value::from_object(meta))?;
+        self.contents = match unrendered.content_type {
+            ContentType::Markdown => markdown_with_images(&body, options, images, &unrendered.dir)?,
+            _ => body,
+        };
+        Ok(())
+    }
+}
+
 // -----
-// The tree for handling putting together all the metadata after it has been written out
+// The site model that all the pages are rendered from
 // -----
 
-/// with the metadata written out traverse the path tree and pull in metadata as needed
-impl Dir<DirPath, PagePath> {
-    pub fn create<'a, T: AsRef<Path>>(
-        &self,
-        out_dir: T,
-        templates: &Environment<'a>,
-    ) -> Result<()> {
-        self.create_with(out_dir, templates, self, self)
+/// a directory of the site with all of its pages and subdirectories, shared by all the renders
+#[derive(Debug)]
+pub struct Section {
+    info: DirInfo,
+    /// the pages of the directory, sorted as the directory asks
+    pages: Vec<PageInfo>,
+    dirs: Vec<Arc<Section>>,
+    /// the info and pages as template values, so they are only converted once
+    info_value: minijinja::Value,
+    pages_value: minijinja::Value,
+}
+
+impl Section {
+    /// the section at `path` within the content, like `logs`, in the language of this section
+    pub fn find_section<T: AsRef<Path>>(self: &Arc<Self>, path: T) -> Option<Arc<Section>> {
+        self.section_at(&self.info.save.join(path))
     }
 
-    fn create_with<'a, T: AsRef<Path>>(
-        &self,
-        out_dir: T,
-        templates: &Environment<'a>,
-        root: &'a Self,
-        parent: &'a Self,
-    ) -> Result<()> {
-        for dir in self.dirs() {
-            fs::create_dir_all(out_dir.as_ref().join(&dir.data.orig))?;
-            dir.create_with(out_dir.as_ref(), templates, root, dir)?;
+    /// the section saved to `save`
+    fn section_at(self: &Arc<Self>, save: &Path) -> Option<Arc<Section>> {
+        if self.info.save == save {
+            return Some(self.clone());
         }
-        for page in self.pages() {
-            page.create_with(out_dir.as_ref(), templates, root, parent)?;
-        }
-        Ok(())
+        self.dirs
+            .iter()
+            .find(|dir| save.starts_with(&dir.info.save))?
+            .section_at(save)
     }
-}
 
-impl Page<PagePath> {
-    fn create_with<'a, T: AsRef<Path>>(
-        &self,
+    /// the page at `path` within the content, like `logs/daily.md`, in the language of this
+    /// section
+    pub fn find_page<T: AsRef<Path>>(self: &Arc<Self>, path: T) -> Option<minijinja::Value> {
+        let path = self.info.save.join(path);
+        // bundles are pages of the directory above them so look in the closest section
+        let section = path
+            .ancestors()
+            .skip(1)
+            .find_map(|dir| self.section_at(dir))?;
+        section
+            .pages
+            .iter()
+            .find(|page| page.save.with_extension("") == path.with_extension(""))
+            .map(minijinja::Value::from_serialize)
+    }
+
+    /// the location of the section in the site
+    pub fn save(&self) -> &Path {
+        &self.info.save
+    }
+
+    /// the pages of the section, sorted as the section asks
+    pub fn pages(&self) -> &[PageInfo] {
+        &self.pages
+    }
+
+    pub fn dirs(&self) -> &[Arc<Section>] {
+        &self.dirs
+    }
+
+    /// render all the pages of the site to the output directory, giving how long each took
+    pub fn create<T: AsRef<Path>>(
+        self: &Arc<Self>,
         out_dir: T,
-        templates: &Environment<'a>,
-        root: &'a Dir<DirPath, PagePath>,
-        parent: &'a Dir<DirPath, PagePath>,
-    ) -> Result<()> {
-        let contents = fs::read_to_string(&self.data.path)?;
-        let metadata: Metadata = serde_yml::from_str(&contents)?;
+        templates: &Environment,
+        minify: bool,
+    ) -> Result<Vec<RenderTime>> {
+        self.create_with(out_dir.as_ref(), templates, minify, self)
+    }
+
+    fn create_with(
+        self: &Arc<Self>,
+        out_dir: &Path,
+        templates: &Environment,
+        minify: bool,
+        root: &Arc<Section>,
+    ) -> Result<Vec<RenderTime>> {
+        let assets = self.copy_assets(out_dir);
+        let index = self
+            .create_index(out_dir, templates, minify, root)
+            .map_err(|e| {
+                self.info.in_file(e).note(format!(
+                    "while rendering the index of `{}`",
+                    self.info.save.display()
+                ))
+            });
+        let dirs: Vec<_> = self
+            .dirs
+            .par_iter()
+            .map(|dir| {
+                fs::create_dir_all(out_dir.join(&dir.info.save))?;
+                dir.create_with(out_dir, templates, minify, root)
+            })
+            .collect();
+        let pages: Vec<_> = self
+            .pages
+            .par_iter()
+            .map(|page| {
+                self.create_page(page, out_dir, templates, minify, root)
+                    .map_err(|e| {
+                        e.in_file(&page.file)
+                            .note(format!("while rendering `{}`", page.save.display()))
+                    })
+            })
+            .collect();
+        let (dirs, pages) = (error::collect(dirs), error::collect(pages));
+        match (assets, index, dirs, pages) {
+            (Ok(()), Ok(index), Ok(dirs), Ok(pages)) => Ok(index
+                .into_iter()
+                .chain(dirs.into_iter().flatten())
+                .chain(pages)
+                .collect()),
+            (assets, index, dirs, pages) => Err(Error::from_errors(
+                [assets.err(), index.err(), dirs.err(), pages.err()]
+                    .into_iter()
+                    .flatten(),
+            )),
+        }
+    }
+
+    /// render a page of the directory
+    fn create_page(
+        self: &Arc<Self>,
+        page: &PageInfo,
+        out_dir: &Path,
+        templates: &Environment,
+        minify: bool,
+        root: &Arc<Section>,
+    ) -> Result<RenderTime> {
+        let start = Instant::now();
+        let save = out_dir.join(&page.save);
+        if page.bundle {
+            let dir = save.parent().unwrap_or(out_dir);
+            fs::create_dir_all(dir)?;
+            for resource in &page.resources {
+                fs::copy(&resource.source, dir.join(&resource.name))
+                    .map_err(|e| Error::from(e).in_file(&resource.source))?;
+            }
+        }
         let meta = MetaObject {
-            root: root.data.clone(),
-            parent: parent.data.clone(),
-            page: metadata,
+            root: root.clone(),
+            parent: self.clone(),
+            page: minijinja::Value::from_serialize(page),
+            paginator: None,
         };
-        let template = templates.get_template(&self.data.template)?;
+        let template = templates.get_template(&page.template)?;
         let content = template.render(minijinja::Value::from_object(meta))?;
-        fs::write(out_dir.as_ref().join(&self.data.orig), content)?;
+        fs::write(save, minified(content, minify, &page.meta.meta))?;
+        Ok(RenderTime {
+            save: page.save.clone(),
+            template: page.template.clone(),
+            time: start.elapsed(),
+        })
+    }
+
+    /// copy the files in the directory that aren't pages to the output
+    fn copy_assets(&self, out_dir: &Path) -> Result<()> {
+        let out_dir = out_dir.join(&self.info.save);
+        fs::create_dir_all(&out_dir)?;
+        for asset in &self.info.assets {
+            let name = asset
+                .file_name()
+                .ok_or_else(|| Error::PageError(asset.clone()))?;
+            fs::copy(asset, out_dir.join(name)).map_err(|e| Error::from(e).in_file(asset))?;
+        }
         Ok(())
     }
+
+    /// write out the index pages for the directory, and its feed if it has one
+    fn create_index(
+        self: &Arc<Self>,
+        out_dir: &Path,
+        templates: &Environment,
+        minify: bool,
+        root: &Arc<Section>,
+    ) -> Result<Vec<RenderTime>> {
+        let info = &self.info;
+        let mut times = Vec::new();
+        if !info.index && !info.feed {
+            return Ok(times);
+        }
+        let meta = MetaObject {
+            root: root.clone(),
+            parent: self.clone(),
+            page: self.info_value.clone(),
+            paginator: None,
+        };
+        let out_dir = out_dir.join(&info.save);
+
+        if info.feed {
+            let start = Instant::now();
+            let template = templates::get_or(templates, FEED_TEMPLATE, templates::DEFAULT_FEED)?;
+            let content = template.render(minijinja::Value::from_object(meta.clone()))?;
+            fs::write(out_dir.join(FEED_FILE), content)?;
+            times.push(RenderTime {
+                save: info.save.join(FEED_FILE),
+                template: FEED_TEMPLATE.into(),
+                time: start.elapsed(),
+            });
+        }
+        if !info.index {
+            return Ok(times);
+        }
+
+        let per_page = info
+            .paginate
+            .filter(|n| *n > 0)
+            .unwrap_or(self.pages.len().max(1));
+        let chunks: Vec<_> = self.pages.chunks(per_page).collect();
+        let total = chunks.len().max(1);
+        for number in 1..=total {
+            let start = Instant::now();
+            let paginator = Paginator {
+                pages: chunks.get(number - 1).copied().unwrap_or_default(),
+                number,
+                total,
+                prev: (number > 1).then(|| listing_url(&info.save, number - 1)),
+                next: (number < total).then(|| listing_url(&info.save, number + 1)),
+            };
+            let meta = MetaObject {
+                paginator: Some(minijinja::Value::from_serialize(paginator)),
+                ..meta.clone()
+            };
+            let template =
+                templates::get_or(templates, &info.template, templates::DEFAULT_SECTION)?;
+            let content = template.render(minijinja::Value::from_object(meta))?;
+            let save = listing_path(&out_dir, number);
+            fs::create_dir_all(&save)?;
+            fs::write(
+                save.join(INDEX_HTML),
+                minified(content, minify, &info.meta.meta),
+            )?;
+            times.push(RenderTime {
+                save: listing_path(&info.save, number).join(INDEX_HTML),
+                template: info.template.clone(),
+                time: start.elapsed(),
+            });
+        }
+        Ok(times)
+    }
 }
 
-#[derive(Debug)]
+/// the values given to the template when rendering a page
+#[derive(Debug, Clone)]
 struct MetaObject {
-    root: DirPath,
-    parent: DirPath,
-    page: Metadata,
+    root: Arc<Section>,
+    parent: Arc<Section>,
+    /// the data of the page being rendered
+    page: minijinja::Value,
+    /// the pages to list on a directory index page
+    paginator: Option<minijinja::Value>,
 }
 
-#[derive(Debug, Clone)]
-pub struct DirPath {
-    path: PathBuf,
-    orig: PathBuf,
+/// a single page of the listing of a directory's pages
+#[derive(Debug, Clone, Serialize)]
+struct Paginator<'a> {
+    /// the pages on this page of the listing
+    pages: &'a [PageInfo],
+    /// the number of this page, starting from 1
+    number: usize,
+    /// the total number of pages
+    total: usize,
+    /// the url of the previous page
+    prev: Option<String>,
+    /// the url of the next page
+    next: Option<String>,
 }
 
-#[derive(Debug, Clone)]
-pub struct PagePath {
-    path: PathBuf,
-    orig: PathBuf,
-    template: String,
+/// the directory that a page of a listing is saved to
+fn listing_path(dir: &Path, number: usize) -> PathBuf {
+    if number == 1 {
+        dir.into()
+    } else {
+        dir.join("page").join(number.to_string())
+    }
 }
 
-impl Object for DirPath {
+/// the url for a page of a listing
+fn listing_url(dir: &Path, number: usize) -> String {
+    let path = listing_path(dir, number).join(INDEX_HTML);
+    format!("/{}", path.to_string_lossy())
+}
+
+impl Object for Section {
     fn get_value(self: &Arc<Self>, key: &minijinja::Value) -> Option<minijinja::Value> {
         match key.as_str()? {
-            "pages" => {
-                let mut pages: Vec<PageInfo> = Vec::new();
-                for entry in self.path.read_dir().ok()? {
-                    let entry = entry.ok()?;
-                    if entry.path().is_file()
-                        && entry.path().file_name() != Some(OsStr::new(META_FILE))
-                    {
-                        let contents = fs::read_to_string(entry.path())
-                            .inspect_err(|e| {
-                                println!("Failed to read `{:?}` with `{}`", entry.path(), e)
-                            })
-                            .ok()?;
-                        let meta = serde_yml::from_str(&contents)
-                            .inspect_err(|err| {
-                                println!("Failed to parse `{:?}` with `{}`", entry.path(), err)
-                            })
-                            .ok()?;
-                        pages.push(meta);
-                    }
-                }
-                Some(minijinja::Value::from_serialize(pages))
-            }
-            "dirs" => {
-                let mut dirs: Vec<DirInfo> = Vec::new();
-                for entry in self.path.read_dir().ok()? {
-                    let entry = entry.ok()?;
-                    if entry.path().is_dir() {
-                        let meta_path = entry.path().join(META_FILE);
-                        let contents = fs::read_to_string(&meta_path)
-                            .inspect_err(|e| println!("Failed to read {:?} with {}", &meta_path, e))
-                            .ok()?;
-                        let meta = serde_yml::from_str(&contents)
-                            .inspect_err(|e| {
-                                println!("Failed to parse `{:?}` with `{}`", &meta_path, e)
-                            })
-                            .ok()?;
-                        dirs.push(meta);
-                    }
-                }
-                Some(minijinja::Value::from_serialize(dirs))
-            }
-            _ => None,
+            "pages" => Some(self.pages_value.clone()),
+            "dirs" => Some(
+                self.dirs
+                    .iter()
+                    .cloned()
+                    .map(minijinja::Value::from_dyn_object)
+                    .collect(),
+            ),
+            _ => self
+                .info_value
+                .get_item(key)
+                .ok()
+                .filter(|v| !v.is_undefined()),
         }
     }
 }
@@ -497,16 +1516,31 @@ impl Object for DirPath {
 impl Object for MetaObject {
     fn get_value(self: &Arc<Self>, key: &minijinja::Value) -> Option<minijinja::Value> {
         match key.as_str()? {
-            "root" => Some(minijinja::Value::from_object(self.root.clone())),
-            "parent" => Some(minijinja::Value::from_object(self.parent.clone())),
-            _ => {
-                let meta = minijinja::Value::from_serialize(self.page.clone());
-                meta.get_item(key).ok()
-            }
+            "root" => Some(minijinja::Value::from_dyn_object(self.root.clone())),
+            "parent" => Some(minijinja::Value::from_dyn_object(self.parent.clone())),
+            "page" => Some(self.page.clone()),
+            "paginator" => self.paginator.clone(),
+            // leave missing keys undefined so that globals like `site` are still visible
+            _ => self.page.get_item(key).ok().filter(|v| !v.is_undefined()),
         }
     }
 }
 
+/// whether the file gives the index of the directory
+fn is_index(path: &Path) -> bool {
+    path.file_name() == Some(OsStr::new(INDEX_FILE))
+}
+
+/// whether the file is the page of a page bundle
+fn is_bundle_index(path: &Path) -> bool {
+    path.file_name() == Some(OsStr::new(BUNDLE_FILE))
+}
+
+/// whether the file gives the defaults for the pages in the directory
+fn is_defaults(path: &Path) -> bool {
+    path.file_name() == Some(OsStr::new(DEFAULTS_FILE))
+}
+
 // check if a direntry is hidden
 #[cfg(unix)]
 fn hidden(entry: &DirEntry) -> bool {
@@ -524,3 +1558,27 @@ fn hidden(entry: &DirEntry) -> bool {
         true
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn page_values_before_directory_defaults_before_rules() {
+        let mut meta: HashMap<String, Value> =
+            serde_yml::from_str("title: Mine\nauthor: me").unwrap();
+        // the `_defaults.yaml` and `cascade` values of the directories
+        let defaults: HashMap<String, Value> =
+            serde_yml::from_str("author: them\ntoc: true").unwrap();
+        let rules = BTreeMap::from([
+            ("author".to_string(), "config".into()),
+            ("toc".to_string(), false.into()),
+            ("template".to_string(), "doc".into()),
+        ]);
+        fill_defaults(&mut meta, &defaults, rules).unwrap();
+        assert_eq!(meta["title"], Value::from("Mine"));
+        assert_eq!(meta["author"], Value::from("me"));
+        assert_eq!(meta["toc"], Value::from(true));
+        assert_eq!(meta["template"], Value::from("doc"));
+    }
+}
@@ -0,0 +1,40 @@
+// search the pages with the index written by sitdown
+(function () {
+	const script = document.currentScript;
+	let pages = null;
+
+	async function load() {
+		if (pages === null) {
+			const response = await fetch(script.dataset.index);
+			pages = await response.json();
+		}
+		return pages;
+	}
+
+	function matches(page, words) {
+		const text = [page.title, (page.headings || []).join(" "), page.body]
+			.join(" ")
+			.toLowerCase();
+		return words.every((word) => text.includes(word));
+	}
+
+	document.addEventListener("DOMContentLoaded", () => {
+		const input = document.getElementById("search");
+		const results = document.getElementById("search-results");
+		input.addEventListener("input", async () => {
+			const words = input.value.toLowerCase().split(/\s+/).filter(Boolean);
+			results.replaceChildren();
+			if (words.length === 0) {
+				return;
+			}
+			for (const page of (await load()).filter((p) => matches(p, words)).slice(0, 10)) {
+				const link = document.createElement("a");
+				link.href = page.url;
+				link.textContent = page.title;
+				const item = document.createElement("li");
+				item.append(link);
+				results.append(item);
+			}
+		});
+	});
+})();